@@ -0,0 +1,189 @@
+//! Movement of monsters declared in the configuration, see `rocket_behaviour` and `mine_behaviour`
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Mode {
+    /// No force
+    Immobile,
+    /// Toward the closest player along the path
+    Attracted,
+    /// Away from the closest player along the path
+    Repulsed,
+    /// Keep going at `force` speed, bounces depend on the restitution
+    Bouncing,
+    /// Turn around the closest player at `radius`
+    Orbiting,
+    /// Toward a random direction drawn every `period` seconds
+    Wandering,
+    /// Toward the closest player from afar but keep `radius` away from it
+    FarHoming,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Motion {
+    pub mode: Mode,
+    #[serde(default)]
+    pub force: f32,
+    /// Force lost per unit of distance to the closest player
+    #[serde(default)]
+    pub falloff: f32,
+    /// Factor applied to the linear velocity at each update, 1.0 keeps it
+    #[serde(default = "one")]
+    pub damping: f32,
+    #[serde(default)]
+    pub radius: f32,
+    #[serde(default = "one")]
+    pub period: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Condition {
+    Always,
+    /// Path distance to the closest player
+    PlayerCloserThan(f32),
+    PlayerFartherThan(f32),
+    /// A player is in line of sight or went out of it for less than the given seconds
+    PlayerInSight(f32),
+}
+
+impl Condition {
+    /// Distance is none if there is no player and last seen is none if no player has been seen
+    pub fn holds(&self, distance: Option<f32>, last_seen: Option<f32>) -> bool {
+        match *self {
+            Condition::Always => true,
+            Condition::PlayerCloserThan(d) => distance.map_or(false, |distance| distance < d),
+            Condition::PlayerFartherThan(d) => distance.map_or(false, |distance| distance > d),
+            Condition::PlayerInSight(memory) => last_seen.map_or(false, |last_seen| last_seen <= memory),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub when: Condition,
+    /// Once its condition has been met the rule holds forever
+    #[serde(default)]
+    pub latch: bool,
+    pub motion: Motion,
+}
+
+/// The motion of the first rule that holds moves the monster, if none holds it is left still
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Behaviour {
+    #[serde(default)]
+    pub restitution: f32,
+    pub rules: Vec<Rule>,
+}
+
+impl Behaviour {
+    /// Latched is updated with the rules whose latching condition holds
+    pub fn motion(
+        &self,
+        latched: &mut Vec<bool>,
+        distance: Option<f32>,
+        last_seen: Option<f32>,
+    ) -> Option<&Motion> {
+        latched.resize(self.rules.len(), false);
+        for (rule, latched) in self.rules.iter().zip(latched.iter_mut()) {
+            if *latched || rule.when.holds(distance, last_seen) {
+                *latched = rule.latch;
+                return Some(&rule.motion);
+            }
+        }
+        None
+    }
+}
+
+impl Motion {
+    /// Force to apply given the direction and the distance to the closest player
+    pub fn force(
+        &self,
+        closest_player: Option<(::na::Vector3<f32>, f32)>,
+        lin_vel: ::na::Vector3<f32>,
+        wander_direction: ::na::Vector3<f32>,
+    ) -> ::na::Vector3<f32> {
+        let toward = closest_player.map(|(vector, distance)| (vector.normalize(), distance));
+        match self.mode {
+            Mode::Immobile | Mode::Bouncing => ::na::zero(),
+            Mode::Attracted | Mode::Repulsed => toward.map_or(::na::zero(), |(direction, distance)| {
+                let force = (self.force - distance * self.falloff).max(0.0);
+                if self.mode == Mode::Attracted {
+                    direction * force
+                } else {
+                    -direction * force
+                }
+            }),
+            Mode::FarHoming => toward.map_or(::na::zero(), |(direction, distance)| {
+                direction * self.force * (distance - self.radius).max(-1.0).min(1.0)
+            }),
+            Mode::Orbiting => toward.map_or(::na::zero(), |(direction, distance)| {
+                // keep turning the way the monster already goes
+                let mut tangent = lin_vel - direction * lin_vel.dot(&direction);
+                if tangent.norm() < 1e-6 {
+                    tangent = direction.cross(&::na::Vector3::z());
+                    if tangent.norm() < 1e-6 {
+                        tangent = direction.cross(&::na::Vector3::x());
+                    }
+                }
+                let correction = ((distance - self.radius) / self.radius.max(1e-6)).max(-1.0).min(1.0);
+                (tangent.normalize() + direction * correction) * self.force
+            }),
+            Mode::Wandering => wander_direction * self.force,
+        }
+    }
+}
+
+#[test]
+fn behaviour_rules() {
+    let behaviour: Behaviour = ::ron::de::from_str(
+        "(
+            rules: [
+                (when: PlayerCloserThan(2.0), latch: true, motion: (mode: Attracted, force: 1.0, falloff: 0.25)),
+                (when: Always, motion: (mode: Immobile, damping: 0.5)),
+            ],
+        )",
+    ).unwrap();
+    assert_eq!(behaviour.restitution, 0.0);
+    assert_eq!(behaviour.rules[0].motion.damping, 1.0);
+
+    let mut latched = vec![];
+    assert_eq!(behaviour.motion(&mut latched, None, None).unwrap().mode, Mode::Immobile);
+    assert_eq!(behaviour.motion(&mut latched, Some(3.0), None).unwrap().mode, Mode::Immobile);
+    assert_eq!(behaviour.motion(&mut latched, Some(1.0), None).unwrap().mode, Mode::Attracted);
+    // the monster keeps chasing once it has seen the player
+    assert_eq!(behaviour.motion(&mut latched, Some(3.0), None).unwrap().mode, Mode::Attracted);
+
+    let condition = Condition::PlayerInSight(1.0);
+    assert!(condition.holds(None, Some(0.5)));
+    assert!(!condition.holds(None, Some(1.5)));
+    assert!(!condition.holds(Some(1.0), None));
+
+    let attracted = &behaviour.rules[0].motion;
+    let player = Some((::na::Vector3::new(2.0, 0.0, 0.0), 2.0));
+    let force = attracted.force(player, ::na::zero(), ::na::zero());
+    assert!((force - ::na::Vector3::new(0.5, 0.0, 0.0)).norm() < 1e-6);
+    let far_player = Some((::na::Vector3::new(8.0, 0.0, 0.0), 8.0));
+    assert_eq!(attracted.force(far_player, ::na::zero(), ::na::zero()), ::na::zero());
+
+    let orbiting = Motion {
+        mode: Mode::Orbiting,
+        radius: 2.0,
+        ..attracted.clone()
+    };
+    let force = orbiting.force(player, ::na::Vector3::y(), ::na::zero());
+    assert!((force - ::na::Vector3::y()).norm() < 1e-6);
+
+    // the default behaviours parse and move monsters toward players
+    for behaviour in &[&::CFG.rocket_behaviour, &::CFG.mine_behaviour] {
+        let motion = behaviour.motion(&mut vec![], Some(0.5), Some(0.0)).unwrap();
+        let force = motion.force(Some((::na::Vector3::x(), 0.5)), ::na::zero(), ::na::zero());
+        assert!(force.x > 0.0);
+    }
+
+    // rockets don't chase players out of sight
+    let motion = ::CFG.rocket_behaviour.motion(&mut vec![], Some(0.5), None).unwrap();
+    assert_eq!(motion.mode, Mode::Immobile);
+}
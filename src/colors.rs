@@ -1,7 +1,7 @@
 const GEN_PALE_DIVISION: usize = 10;
 const GEN_PALE_DELTA: f32 = 0.0;
 
-#[derive(EnumIterator)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIterator)]
 #[repr(usize)]
 pub enum GenPale {
     Color0,
@@ -21,19 +21,80 @@ pub enum GenPale {
 #[test]
 fn alignment() {
     assert!(GenPale::iter_variants().count() == GEN_PALE_DIVISION + 2);
-    assert!(GenPale::Black as usize = GEN_PALE_DIVISION);
+    assert!(GenPale::Black as usize == GEN_PALE_DIVISION);
+    for palette in Palette::iter_variants() {
+        assert!(palette.len() <= GEN_PALE_DIVISION);
+    }
 }
 
 impl GenPale {
-    // all colors, not black nor white
+    // distinct colors of the configured palette, not black nor white
     pub fn colors() -> Vec<Self> {
-        GenPale::iter_variants().collect::<Vec<_>>()
+        GenPale::iter_variants()
+            .take(::CFG.palette.len())
+            .collect::<Vec<_>>()
+    }
+}
+
+/// Colors the `GenPale` variants are mapped onto
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIterator, Serialize, Deserialize)]
+pub enum Palette {
+    /// Hues evenly spread on the color circle
+    Rainbow,
+    /// Okabe and Ito palette, distinguishable with any color vision deficiency
+    OkabeIto,
+    /// Paul Tol bright palette, colorblind safe
+    TolBright,
+    /// Paul Tol muted palette, colorblind safe
+    TolMuted,
+}
+
+const OKABE_ITO: [u32; 7] = [0xE69F00, 0x56B4E9, 0x009E73, 0xF0E442, 0x0072B2, 0xD55E00, 0xCC79A7];
+const TOL_BRIGHT: [u32; 7] = [0x4477AA, 0xEE6677, 0x228833, 0xCCBB44, 0x66CCEE, 0xAA3377, 0xBBBBBB];
+const TOL_MUTED: [u32; 9] = [
+    0xCC6677, 0x332288, 0xDDCC77, 0x117733, 0x88CCEE, 0x882255, 0x44AA99, 0x999933, 0xAA4499,
+];
+
+impl Palette {
+    /// Number of distinct colors
+    pub fn len(&self) -> usize {
+        match *self {
+            Palette::Rainbow => GEN_PALE_DIVISION,
+            Palette::OkabeIto => OKABE_ITO.len(),
+            Palette::TolBright => TOL_BRIGHT.len(),
+            Palette::TolMuted => TOL_MUTED.len(),
+        }
+    }
+
+    /// Linear colors with components between 0 and 1
+    fn linear_colors(&self) -> Vec<[f32; 3]> {
+        let hex = match *self {
+            Palette::Rainbow => {
+                return (0..GEN_PALE_DIVISION)
+                    .map(|i| color_circle((i as f32 + GEN_PALE_DELTA) / GEN_PALE_DIVISION as f32))
+                    .collect();
+            }
+            Palette::OkabeIto => &OKABE_ITO[..],
+            Palette::TolBright => &TOL_BRIGHT[..],
+            Palette::TolMuted => &TOL_MUTED[..],
+        };
+        hex.iter()
+            .map(|hex| {
+                let srgb = ::palette::Srgb::new(
+                    (hex >> 16 & 0xFF) as f32 / 255.0,
+                    (hex >> 8 & 0xFF) as f32 / 255.0,
+                    (hex & 0xFF) as f32 / 255.0,
+                );
+                let lin = srgb.into_linear();
+                [lin.red, lin.green, lin.blue]
+            })
+            .collect()
     }
 }
 
 lazy_static! {
    static ref  GEN_PALE_GENERATION: Vec<[f32; 3]> =
-       generate_colors(GEN_PALE_DIVISION, GEN_PALE_DELTA, ::CFG.color_black, ::CFG.color_white);
+       generate_colors(::CFG.palette, ::CFG.color_black, ::CFG.color_white);
 }
 
 impl Into<[f32; 3]> for GenPale {
@@ -42,18 +103,14 @@ impl Into<[f32; 3]> for GenPale {
     }
 }
 
-fn generate_colors(division: usize, delta: f32, black: f32, white: f32) -> Vec<[f32; 3]> {
+/// Colors indexed by `GenPale`, palettes smaller than the division are repeated
+fn generate_colors(palette: Palette, black: f32, white: f32) -> Vec<[f32; 3]> {
     let mut colors = vec![];
     assert!(black < white);
 
-    // Black
-    colors.push([black, black, black]);
-
-    // White
-    colors.push([white, white, white]);
-
-    for i in 0..division {
-        let color = color_circle((i as f32 + delta) / division as f32);
+    let palette_colors = palette.linear_colors();
+    for i in 0..GEN_PALE_DIVISION {
+        let color = palette_colors[i % palette_colors.len()];
         colors.push([
             color[0] * (white - black) + black,
             color[1] * (white - black) + black,
@@ -61,6 +118,12 @@ fn generate_colors(division: usize, delta: f32, black: f32, white: f32) -> Vec<[
         ]);
     }
 
+    // Black
+    colors.push([black, black, black]);
+
+    // White
+    colors.push([white, white, white]);
+
     // Convert from Srgb to Unorm
     for color in &mut colors {
         let lin_color = ::palette::LinSrgb::new(color[0], color[1], color[2]);
@@ -24,7 +24,10 @@ impl ::specs::Component for Target {
 }
 
 pub struct ClosestPlayer {
+    /// Direction to follow to reach the player
     pub vector: Option<::na::Vector3<f32>>,
+    /// Path distance to the player
+    pub distance: f32,
 }
 impl ::specs::Component for ClosestPlayer {
     type Storage = ::specs::VecStorage<Self>;
@@ -33,26 +36,86 @@ impl ClosestPlayer {
     pub fn new() -> Self {
         ClosestPlayer {
             vector: None,
+            distance: 0.0,
         }
     }
 }
 
+/// Players in line of sight, walls block the sight
 #[derive(Default)]
-pub struct MineControl;
-impl ::specs::Component for MineControl {
-    type Storage = ::specs::NullStorage<Self>;
+pub struct Visibility {
+    /// Visible players and for how long they have been visible
+    pub visible: Vec<(::specs::Entity, f32)>,
+    /// Time since a player was last in sight, zero while one is visible and none if no player
+    /// has ever been seen
+    pub last_seen: Option<f32>,
+}
+impl ::specs::Component for Visibility {
+    type Storage = ::specs::VecStorage<Self>;
+}
+impl Visibility {
+    pub fn sees(&self, player: ::specs::Entity) -> bool {
+        self.visible.iter().any(|&(visible, _)| visible == player)
+    }
 }
 
-#[derive(Default)]
-pub struct RocketControl;
-impl ::specs::Component for RocketControl {
-    type Storage = ::specs::NullStorage<Self>;
+pub struct Behaviour {
+    pub behaviour: ::behaviour::Behaviour,
+    /// Rules whose latching condition has been met
+    pub latched: Vec<bool>,
+    pub wander_direction: ::na::Vector3<f32>,
+    /// Time before drawing another wander direction
+    pub wander_timer: f32,
+}
+impl ::specs::Component for Behaviour {
+    type Storage = ::specs::VecStorage<Self>;
+}
+impl Behaviour {
+    pub fn new(behaviour: ::behaviour::Behaviour) -> Self {
+        Behaviour {
+            behaviour,
+            latched: vec![],
+            wander_direction: ::na::zero(),
+            wander_timer: 0.0,
+        }
+    }
 }
 
-#[derive(Default)]
-pub struct PlayerKiller;
+/// What hurts players, each player has a tolerance to each hazard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIterator)]
+pub enum Hazard {
+    Wall,
+    Rocket,
+    Mine,
+}
+
+/// Damage players on contact and is destroyed
+pub struct PlayerKiller {
+    pub hazard: Hazard,
+    pub damage: f32,
+}
 impl ::specs::Component for PlayerKiller {
-    type Storage = ::specs::NullStorage<Self>;
+    type Storage = ::specs::VecStorage<Self>;
+}
+
+pub struct Health {
+    pub health: f32,
+    /// Time left during which damages are ignored
+    pub invulnerable: f32,
+    /// Velocity at the end of the last update, used for impacts on walls
+    pub velocity: ::na::Vector3<f32>,
+}
+impl ::specs::Component for Health {
+    type Storage = ::specs::VecStorage<Self>;
+}
+impl Health {
+    pub fn new() -> Self {
+        Health {
+            health: ::CFG.player_health,
+            invulnerable: ::CFG.invulnerability_time,
+            velocity: ::na::zero(),
+        }
+    }
 }
 
 pub struct FlightControl {
@@ -218,3 +281,24 @@ impl Proximitor {
         }
     }
 }
+
+/// Ride along a rail of `resource::Rails`
+#[derive(Clone, Copy, Debug)]
+pub struct Ride {
+    pub rail: usize,
+    /// Distance travelled from the entered end
+    pub distance: f32,
+    /// Whereas the rail was entered from its end
+    pub reverse: bool,
+}
+
+/// Entity able to ride tubes
+#[derive(Default)]
+pub struct Rider {
+    pub ride: Option<Ride>,
+    /// Rail end the rider left, it can't be entered again until the rider moves away
+    pub left_end: Option<::na::Vector3<f32>>,
+}
+impl ::specs::Component for Rider {
+    type Storage = ::specs::VecStorage<Self>;
+}
@@ -29,19 +29,49 @@ pub struct Configuration {
 
     pub color_black: f32,
     pub color_white: f32,
+    pub palette: ::colors::Palette,
 
-    pub rocket_control_lin_damping: f32,
-    pub rocket_control_force: f32,
+    pub tile_max_size: isize,
+    pub tile_size_weight: f32,
+    pub tile_subdivision: isize,
+
+    pub rocket_behaviour: ::behaviour::Behaviour,
+    pub mine_behaviour: ::behaviour::Behaviour,
 
     pub ball_radius: f32,
+
+    pub player_health: f32,
+    /// Time during which damages are ignored after one and after spawning
+    pub invulnerability_time: f32,
+    /// Speed toward a wall under which impacts don't hurt
+    pub wall_damage_min_speed: f32,
+    /// Damage per unit of speed above the minimal speed
+    pub wall_damage_coef: f32,
+    pub rocket_damage: f32,
+    pub mine_damage: f32,
+    pub tolerance_step: f32,
+    pub tolerance_max: f32,
+
+    /// Lives of levels that don't set them
+    pub lives: usize,
+    /// Time before a dead player respawns
+    pub respawn_delay: f32,
     pub rocket_launcher_timer: f32,
 
-    pub mine_control_max_force: f32,
-    pub mine_control_coef_force: f32,
+    pub rail_speed: f32,
+    pub rail_exit_impulse: f32,
+    pub rail_entry_distance: f32,
 
     pub text_scale: f32,
+
+    /// Palette indices of entities in imported vox models
+    pub vox_markers: ::vox::VoxMarkers,
 }
 
 impl Configuration {
-    fn check(&self) {}
+    fn check(&self) {
+        assert!(self.tile_max_size >= 1, "tile_max_size must be at least 1");
+        assert!(self.tile_subdivision >= 1, "tile_subdivision must be at least 1");
+        assert!(self.tolerance_step > 0.0, "tolerance_step must be positive");
+    }
 }
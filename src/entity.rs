@@ -1,4 +1,3 @@
-use alga::linear::AffineTransformation;
 use specs::Builder;
 
 #[repr(usize)]
@@ -8,10 +7,13 @@ pub enum Group {
     Player,
     Rocket,
     Mine,
+    /// Tubes are walls riders go through
+    Tube,
 }
 
-pub fn create_wall(pos: ::na::Vector3<f32>, _color: usize, world: &mut ::specs::World) {
-    let shape = ::ncollide::shape::Cuboid3::new(::na::Vector3::from_element(0.5));
+/// Static cuboid covering one or more wall cells
+pub fn create_wall(pos: ::na::Vector3<f32>, half_extents: ::na::Vector3<f32>, world: &mut ::specs::World) {
+    let shape = ::ncollide::shape::Cuboid3::new(half_extents);
     let mut body = ::nphysics::object::RigidBody::new_static(shape, 0.0, 0.0);
     body.set_transformation(::na::Isometry3::new(pos, ::na::zero()));
 
@@ -29,7 +31,7 @@ pub fn create_wall(pos: ::na::Vector3<f32>, _color: usize, world: &mut ::specs::
     );
 }
 
-pub fn create_player(pos: ::na::Vector3<f32>, world: &::specs::World) {
+pub fn create_player(player: usize, pos: ::na::Vector3<f32>, world: &::specs::World) {
     let shape = ::ncollide::shape::Ball::new(::CFG.ball_radius);
     let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_dynamic();
     group.set_membership(&[Group::Player as usize]);
@@ -40,6 +42,9 @@ pub fn create_player(pos: ::na::Vector3<f32>, world: &::specs::World) {
 
     let entity = world.entities().create();
     world.write_storage().insert(entity, ::component::Player).unwrap();
+    world.write_storage().insert(entity, ::component::Health::new()).unwrap();
+    world.write_storage().insert(entity, ::component::Contactor::new()).unwrap();
+    world.write_storage().insert(entity, ::component::Rider::default()).unwrap();
     world.write_storage().insert(entity, ::component::FlightControl {
         x_direction: 0.0,
         y_direction: 0.0,
@@ -58,44 +63,24 @@ pub fn create_player(pos: ::na::Vector3<f32>, world: &::specs::World) {
         &mut world.write_resource(),
     );
 
-    world.write_resource::<::resource::PlayersEntities>()[0] = Some(entity);
+    world.write_resource::<::resource::PlayersEntities>()[player] = Some(entity);
 }
 
 pub fn create_tube(tube: &::tube::Tube, world: &mut ::specs::World) {
-    let bodies = match tube.shape {
-        ::tube::Shape::Line => {
-            let half_extents = ::na::Vector3::new(::tube::RADIUS, 0.5, ::tube::RADIUS);
+    let bodies = tube.shape
+        .arm_cuboids()
+        .into_iter()
+        .map(|(position, half_extents)| {
             let shape = ::ncollide::shape::Cuboid::new(half_extents);
             let mut body = ::nphysics::object::RigidBody::new_static(shape, 0.0, 0.0);
-            body.set_transformation(tube.position);
-            vec![body]
-        }
-        ::tube::Shape::Angle => {
-            let half_extents = ::na::Vector3::new(::tube::RADIUS, 0.25, ::tube::RADIUS);
-            let shape = ::ncollide::shape::Cuboid::new(half_extents);
-            let mut body_0 = ::nphysics::object::RigidBody::new_static(shape, 0.0, 0.0);
-            let y =
-                (tube.position.rotation * ::na::Point::from_coordinates(::na::Vector3::y())).coords;
-            let position = tube.position
-                .append_translation(&::na::Translation::from_vector(-y * 0.25));
-            body_0.set_transformation(position);
-
-            let half_extents = ::na::Vector3::new(0.25, ::tube::RADIUS, ::tube::RADIUS);
-            let shape = ::ncollide::shape::Cuboid::new(half_extents);
-            let mut body_1 = ::nphysics::object::RigidBody::new_static(shape, 0.0, 0.0);
-            let x =
-                (tube.position.rotation * ::na::Point::from_coordinates(::na::Vector3::x())).coords;
-            let position = tube.position
-                .append_translation(&::na::Translation::from_vector(x * 0.25));
-            body_1.set_transformation(position);
-
-            vec![body_0, body_1]
-        }
-    };
+            body.set_transformation(tube.position * position);
+            body
+        })
+        .collect::<Vec<_>>();
 
     for mut body in bodies {
         let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_static();
-        group.set_membership(&[Group::Wall as usize]);
+        group.set_membership(&[Group::Wall as usize, Group::Tube as usize]);
         body.set_collision_groups(group);
 
         let entity = world.create_entity().build();
@@ -113,14 +98,19 @@ pub fn create_rocket(pos: ::na::Isometry3<f32>, world: &::specs::World) {
     let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_dynamic();
     group.set_membership(&[Group::Rocket as usize]);
     let shape = ::ncollide::shape::Ball::new(::CFG.ball_radius);
-    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    let restitution = ::CFG.rocket_behaviour.restitution;
+    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, restitution, 0.0);
     body.set_collision_groups(group);
     body.set_transformation(pos);
 
     let entity = world.entities().create();
-    world.write_storage().insert(entity, ::component::PlayerKiller).unwrap();
+    world.write_storage().insert(entity, ::component::PlayerKiller {
+        hazard: ::component::Hazard::Rocket,
+        damage: ::CFG.rocket_damage,
+    }).unwrap();
     world.write_storage().insert(entity, ::component::Contactor::new()).unwrap();
-    world.write_storage().insert(entity, ::component::RocketControl).unwrap();
+    world.write_storage().insert(entity, ::component::Behaviour::new(::CFG.rocket_behaviour.clone())).unwrap();
+    world.write_storage().insert(entity, ::component::Visibility::default()).unwrap();
     world.write_storage().insert(entity, ::component::ClosestPlayer::new()).unwrap();
 
     ::component::PhysicBody::add(
@@ -134,6 +124,7 @@ pub fn create_rocket(pos: ::na::Isometry3<f32>, world: &::specs::World) {
 pub fn create_rocket_launcher(pos: ::na::Isometry3<f32>, world: &mut ::specs::World) {
     world.create_entity()
         .with(::component::RocketLauncher::new(pos))
+        .with(::component::Visibility::default())
         .build();
 }
 
@@ -141,14 +132,19 @@ pub fn create_mine(pos: ::na::Vector3<f32>, world: &::specs::World) {
     let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_dynamic();
     group.set_membership(&[Group::Mine as usize]);
     let shape = ::ncollide::shape::Ball::new(::CFG.ball_radius);
-    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    let restitution = ::CFG.mine_behaviour.restitution;
+    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, restitution, 0.0);
     body.set_collision_groups(group);
     body.set_transformation(::na::Isometry3::new(pos, ::na::zero()));
 
     let entity = world.entities().create();
-    world.write_storage().insert(entity, ::component::PlayerKiller).unwrap();
+    world.write_storage().insert(entity, ::component::PlayerKiller {
+        hazard: ::component::Hazard::Mine,
+        damage: ::CFG.mine_damage,
+    }).unwrap();
     world.write_storage().insert(entity, ::component::Contactor::new()).unwrap();
-    world.write_storage().insert(entity, ::component::MineControl).unwrap();
+    world.write_storage().insert(entity, ::component::Behaviour::new(::CFG.mine_behaviour.clone())).unwrap();
+    world.write_storage().insert(entity, ::component::Visibility::default()).unwrap();
     world.write_storage().insert(entity, ::component::ClosestPlayer::new()).unwrap();
 
     ::component::PhysicBody::add(
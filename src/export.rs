@@ -0,0 +1,294 @@
+//! Export of the geometry of a level to Wavefront OBJ or binary glTF
+
+use graphics::Vertex;
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Triangles of one material, in world coordinates
+pub struct Group {
+    pub name: String,
+    /// Srgb color
+    pub color: [f32; 3],
+    pub vertices: Vec<Vertex>,
+}
+
+pub struct Geometry {
+    pub groups: Vec<Group>,
+}
+
+#[derive(Debug)]
+pub struct UnknownFormat {
+    path: String,
+}
+
+impl fmt::Display for UnknownFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "unknown export format, expected .obj or .glb: {}", self.path)
+    }
+}
+
+impl ::std::error::Error for UnknownFormat {
+    fn description(&self) -> &str {
+        "unknown export format"
+    }
+}
+
+/// Export the level file to the output file, the format is chosen from its extension
+pub fn export_level(level: &str, output: &str) -> Result<(), ::failure::Error> {
+    let level = ::level::LevelDescription::from_file(level)?;
+    Geometry::from_level(&level).write(output)
+}
+
+impl Geometry {
+    pub fn new(tiles: &[::tile::Tile], tubes: &[::tube::Tube], tile_subdivision: isize) -> Self {
+        let mut groups: Vec<(::colors::GenPale, Group)> = vec![];
+        for tile in tiles {
+            let index = match groups.iter().position(|&(color, _)| color == tile.color) {
+                Some(index) => index,
+                None => {
+                    groups.push((
+                        tile.color,
+                        Group {
+                            name: format!("tiles_{:?}", tile.color).to_lowercase(),
+                            color: tile.color.into(),
+                            vertices: vec![],
+                        },
+                    ));
+                    groups.len() - 1
+                }
+            };
+            let vertices = ::obj::generate_tile(tile.size, tile_subdivision);
+            groups[index].1.vertices.extend(transform(&tile.position, vertices));
+        }
+        // keep files stable
+        groups.sort_by_key(|&(color, _)| color as usize);
+        let mut groups = groups.into_iter().map(|(_, group)| group).collect::<Vec<_>>();
+
+        let mut tube_group = Group {
+            name: "tubes".to_string(),
+            color: ::colors::GenPale::Black.into(),
+            vertices: vec![],
+        };
+        for tube in tubes {
+            let vertices = ::obj::generate_tube(tube.shape);
+            tube_group.vertices.extend(transform(&tube.position, vertices));
+        }
+        if !tube_group.vertices.is_empty() {
+            groups.push(tube_group);
+        }
+
+        Geometry { groups }
+    }
+
+    pub fn from_level(level: &::level::LevelDescription) -> Self {
+        Geometry::new(&level.tiles(), &level.tube_pieces(), ::CFG.tile_subdivision)
+    }
+
+    /// Write `.obj` files with their `.mtl` next to them, or `.glb` files
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), ::failure::Error> {
+        let path = path.as_ref();
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("obj") => {
+                let mtl_path = path.with_extension("mtl");
+                let mtl_name = mtl_path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or("level.mtl");
+                let (obj, mtl) = self.to_obj(mtl_name);
+                File::create(path)?.write_all(obj.as_bytes())?;
+                File::create(&mtl_path)?.write_all(mtl.as_bytes())?;
+            }
+            Some("glb") => {
+                File::create(path)?.write_all(&self.to_glb())?;
+            }
+            _ => {
+                return Err(UnknownFormat {
+                    path: path.display().to_string(),
+                }.into())
+            }
+        }
+        Ok(())
+    }
+
+    /// OBJ and MTL contents, the OBJ refers to the MTL by the name
+    pub fn to_obj(&self, mtl_name: &str) -> (String, String) {
+        let mut obj = format!("mtllib {}\n", mtl_name);
+        let mut mtl = String::new();
+        let mut index = 1;
+        for group in &self.groups {
+            obj.push_str(&format!("o {}\nusemtl {}\n", group.name, group.name));
+            for vertex in &group.vertices {
+                let p = vertex.position;
+                obj.push_str(&format!("v {:.6} {:.6} {:.6}\n", p[0], p[1], p[2]));
+            }
+            for vertex in &group.vertices {
+                let t = vertex.tex_coords;
+                obj.push_str(&format!("vt {:.6} {:.6}\n", t[0], t[1]));
+            }
+            for _ in 0..group.vertices.len() / 3 {
+                obj.push_str(&format!(
+                    "f {0}/{0} {1}/{1} {2}/{2}\n",
+                    index,
+                    index + 1,
+                    index + 2
+                ));
+                index += 3;
+            }
+
+            let c = group.color;
+            mtl.push_str(&format!(
+                "newmtl {}\nKd {:.6} {:.6} {:.6}\n\n",
+                group.name, c[0], c[1], c[2]
+            ));
+        }
+        (obj, mtl)
+    }
+
+    /// Binary glTF with one mesh holding a primitive per group
+    pub fn to_glb(&self) -> Vec<u8> {
+        let mut bin = vec![];
+        let mut buffer_views = vec![];
+        let mut accessors = vec![];
+        let mut primitives = vec![];
+        let mut materials = vec![];
+        for (i, group) in self.groups.iter().enumerate() {
+            let count = group.vertices.len();
+
+            let offset = bin.len();
+            let mut min = [::std::f32::MAX; 3];
+            let mut max = [::std::f32::MIN; 3];
+            for vertex in &group.vertices {
+                for j in 0..3 {
+                    push_f32(&mut bin, vertex.position[j]);
+                    min[j] = min[j].min(vertex.position[j]);
+                    max[j] = max[j].max(vertex.position[j]);
+                }
+            }
+            buffer_views.push(format!(
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{}}}"#,
+                offset,
+                bin.len() - offset
+            ));
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":5126,"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}}"#,
+                buffer_views.len() - 1,
+                count,
+                min[0], min[1], min[2],
+                max[0], max[1], max[2]
+            ));
+
+            let offset = bin.len();
+            for vertex in &group.vertices {
+                push_f32(&mut bin, vertex.tex_coords[0]);
+                push_f32(&mut bin, vertex.tex_coords[1]);
+            }
+            buffer_views.push(format!(
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{}}}"#,
+                offset,
+                bin.len() - offset
+            ));
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":5126,"count":{},"type":"VEC2"}}"#,
+                buffer_views.len() - 1,
+                count
+            ));
+
+            primitives.push(format!(
+                r#"{{"attributes":{{"POSITION":{},"TEXCOORD_0":{}}},"material":{}}}"#,
+                accessors.len() - 2,
+                accessors.len() - 1,
+                i
+            ));
+
+            // glTF colors are linear
+            let c = group.color;
+            let color = ::palette::Srgb::new(c[0], c[1], c[2]).into_linear();
+            materials.push(format!(
+                r#"{{"name":"{}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},1.0],"metallicFactor":0.0}}}}"#,
+                group.name, color.red, color.green, color.blue
+            ));
+        }
+
+        let mut json = format!(
+            r#"{{"asset":{{"version":"2.0","generator":"sese"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0}}],"meshes":[{{"primitives":[{}]}}],"materials":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+            primitives.join(","),
+            materials.join(","),
+            accessors.join(","),
+            buffer_views.join(","),
+            bin.len()
+        ).into_bytes();
+
+        // chunks are aligned on 4 bytes
+        while json.len() % 4 != 0 {
+            json.push(b' ');
+        }
+        while bin.len() % 4 != 0 {
+            bin.push(0);
+        }
+
+        let mut glb = vec![];
+        glb.extend_from_slice(b"glTF");
+        push_u32(&mut glb, 2);
+        push_u32(&mut glb, (12 + 8 + json.len() + 8 + bin.len()) as u32);
+        push_u32(&mut glb, json.len() as u32);
+        glb.extend_from_slice(b"JSON");
+        glb.extend_from_slice(&json);
+        push_u32(&mut glb, bin.len() as u32);
+        glb.extend_from_slice(b"BIN\0");
+        glb.extend_from_slice(&bin);
+        glb
+    }
+}
+
+fn transform(position: &::na::Isometry3<f32>, vertices: Vec<Vertex>) -> Vec<Vertex> {
+    vertices
+        .into_iter()
+        .map(|mut vertex| {
+            let p = vertex.position;
+            let p = position * ::na::Point3::new(p[0], p[1], p[2]);
+            vertex.position = [p[0], p[1], p[2]];
+            vertex
+        })
+        .collect()
+}
+
+fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    for i in 0..4 {
+        bytes.push((value >> (8 * i)) as u8);
+    }
+}
+
+fn push_f32(bytes: &mut Vec<u8>, value: f32) {
+    push_u32(bytes, value.to_bits());
+}
+
+#[test]
+fn tile_and_tube_export() {
+    let tiles = vec![::tile::Tile {
+        position: ::na::Isometry3::identity(),
+        size: ::tile::TileSize::new(1, 1),
+        width: 1.0,
+        height: 1.0,
+        color: ::colors::GenPale::Color0,
+    }];
+    let tubes = vec![::tube::Tube {
+        position: ::na::Isometry3::new(::na::Vector3::new(2.0, 0.0, 0.0), ::na::zero()),
+        shape: ::tube::Shape::Cross,
+    }];
+    let geometry = Geometry::new(&tiles, &tubes, 1);
+    assert_eq!(geometry.groups.len(), 2);
+    let vertices = geometry.groups.iter().map(|g| g.vertices.len()).sum::<usize>();
+
+    let (obj, mtl) = geometry.to_obj("level.mtl");
+    assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), vertices);
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), vertices / 3);
+    assert_eq!(mtl.lines().filter(|l| l.starts_with("newmtl ")).count(), 2);
+
+    let glb = geometry.to_glb();
+    assert_eq!(&glb[0..4], b"glTF");
+    let length = (0..4).map(|i| (glb[8 + i] as usize) << (8 * i)).sum::<usize>();
+    assert_eq!(length, glb.len());
+    assert_eq!(glb.len() % 4, 0);
+}
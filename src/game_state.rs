@@ -1,6 +1,7 @@
 use specs::World;
 use gilrs::{Axis, Button, EventType};
 use world_action::WorldAction;
+use show_message::OkOrShow;
 
 pub trait GameState {
     fn update_draw_ui(self: Box<Self>, world: &mut World) -> Box<GameState>;
@@ -211,49 +212,82 @@ pub struct Game {
 }
 
 impl Game {
-    pub fn new() -> Self {
+    pub fn new(world: &::specs::World) -> Self {
+        let tolerances = world.read_resource::<::resource::PlayersTolerances>();
         Game {
             players_menus: [
-                Some(Game::create_menu()),
-                Some(Game::create_menu()),
-                Some(Game::create_menu()),
+                Some(Game::create_menu(&tolerances[0])),
+                Some(Game::create_menu(&tolerances[1])),
+                Some(Game::create_menu(&tolerances[2])),
             ],
             space_return: [false; 2],
         }
     }
 
-    fn create_menu() -> ::menu::Menu<GameMenuAction> {
+    fn create_menu(tolerance: &::resource::Tolerance) -> ::menu::Menu<GameMenuAction> {
         use self::GameMenuAction::*;
-        ::menu::MenuBuilder::new()
-            .add_middle("Resume".to_string(), Resume)
+        let mut builder = ::menu::MenuBuilder::new()
+            .add_middle("Resume".to_string(), Resume);
+        for hazard in ::component::Hazard::iter_variants() {
+            builder = builder.add_left_right(
+                Game::tolerance_name(hazard, tolerance),
+                ReduceTolerance(hazard),
+                IncreaseTolerance(hazard),
+            );
+        }
+        builder
             .add_middle("Main".to_string(), MainMenu)
             .build()
     }
 
+    fn tolerance_name(hazard: ::component::Hazard, tolerance: &::resource::Tolerance) -> String {
+        format!("{:?} tolerance: {:.2}", hazard, tolerance.get(hazard))
+    }
+
     fn process_action(mut self: Box<Self>, player: usize, action: GameMenuAction, world: &mut World) -> Box<GameState> {
         use self::GameMenuAction::*;
         match action {
             Resume => self.players_menus[player] = None,
+            ReduceTolerance(hazard) | IncreaseTolerance(hazard) => {
+                let steps = if let IncreaseTolerance(_) = action { 1.0 } else { -1.0 };
+                let mut tolerances = world.write_resource::<::resource::PlayersTolerances>();
+                tolerances[player].shift(hazard, steps);
+                let button = 1 + ::component::Hazard::iter_variants().position(|h| h == hazard).unwrap();
+                if let Some(ref mut menu) = self.players_menus[player] {
+                    menu.reset_name(button, Game::tolerance_name(hazard, &tolerances[player]));
+                }
+            }
             MainMenu => return Box::new(GlobalMenu::new(world)) as Box<_>,
         }
         self
     }
 }
 
-// TODO: add tolerance
-//       add disconnect device
+// TODO: add disconnect device
 #[derive(Clone, Copy)]
 enum GameMenuAction {
     Resume,
+    ReduceTolerance(::component::Hazard),
+    IncreaseTolerance(::component::Hazard),
     MainMenu,
 }
 
 impl GameState for Game {
     fn update_draw_ui(self: Box<Self>, world: &mut World) -> Box<GameState> {
+        let game_over = {
+            let number_of_player = world.read_resource::<::resource::Mode>().number_of_player();
+            let players_entities = world.read_resource::<::resource::PlayersEntities>();
+            world.read_resource::<::resource::PlayersLives>().game_over(number_of_player, &players_entities)
+        };
+        if game_over {
+            return Box::new(GameOver::new()) as Box<_>;
+        }
+
         let mut text = world.write_resource::<::resource::Text>();
         let font = world.read_resource::<::resource::Font>();
         let mode = world.read_resource::<::resource::Mode>();
         let players_controllers = world.read_resource::<::resource::PlayersControllers>();
+        let players_lives = world.read_resource::<::resource::PlayersLives>();
 
         text.global = vec![];
 
@@ -271,6 +305,14 @@ impl GameState for Game {
                 text.players[player] = menu.glyphs(&font);
             } else if number_of_player != number_of_controllers {
                 text.players[player] = ::util::menu_layout(vec!["Waiting for other players".to_string()], None, &font);
+            } else if let Some(timer) = players_lives[player].respawn_timer {
+                let lines = vec![
+                    format!("Respawn in {}", timer.ceil()),
+                    format!("Lives: {}", players_lives[player].lives),
+                ];
+                text.players[player] = ::util::menu_layout(lines, None, &font);
+            } else if players_lives[player].lives == 0 && world.read_resource::<::resource::PlayersEntities>()[player].is_none() {
+                text.players[player] = ::util::menu_layout(vec!["Out of lives".to_string()], None, &font);
             } else {
                 text.players[player] = vec![];
             }
@@ -332,7 +374,8 @@ impl GameState for Game {
                 if self.players_menus[player].is_some() {
                     self.players_menus[player] = None;
                 } else {
-                    self.players_menus[player] = Some(Game::create_menu());
+                    let tolerances = world.read_resource::<::resource::PlayersTolerances>();
+                    self.players_menus[player] = Some(Game::create_menu(&tolerances[player]));
                 }
             }
             self
@@ -479,6 +522,92 @@ impl GameState for Game {
     }
 }
 
+pub struct GameOver {
+    menu: ::menu::Menu<GameOverAction>,
+}
+
+impl GameOver {
+    pub fn new() -> Self {
+        use self::GameOverAction::*;
+
+        let menu = ::menu::MenuBuilder::new()
+            .add_middle("Retry".to_string(), Retry)
+            .add_middle("Main".to_string(), MainMenu)
+            .build();
+
+        GameOver {
+            menu,
+        }
+    }
+
+    fn process_action(self: Box<Self>, action: GameOverAction, world: &mut World) -> Box<GameState> {
+        use self::GameOverAction::*;
+        match action {
+            Retry => {
+                let level = world.read_resource::<::resource::CurrentLevel>().0.clone();
+                level.build(world);
+                Box::new(Game::new(world)) as Box<_>
+            }
+            MainMenu => Box::new(GlobalMenu::new(world)) as Box<_>,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum GameOverAction {
+    Retry,
+    MainMenu,
+}
+
+impl GameState for GameOver {
+    fn update_draw_ui(self: Box<Self>, world: &mut World) -> Box<GameState> {
+        let mut text = world.write_resource::<::resource::Text>();
+        let font = world.read_resource::<::resource::Font>();
+        text.players = Default::default();
+        text.global = self.menu.glyphs(&font);
+
+        self
+    }
+
+    fn winit_event(mut self: Box<Self>, event: ::winit::Event, world: &mut World) -> Box<GameState> {
+        let action = {
+            let controllers = world.read_resource::<::resource::PlayersControllers>();
+            self.menu.winit_event(event, None, &controllers)
+        };
+        if let Some(action) = action {
+            self.process_action(action, world)
+        } else {
+            self
+        }
+    }
+
+    fn gilrs_event(
+        mut self: Box<Self>,
+        _id: usize,
+        event: ::gilrs::EventType,
+        world: &mut World,
+    ) -> Box<GameState> {
+        if let Some(action) = self.menu.gilrs_event(event) {
+            self.process_action(action, world)
+        } else {
+            self
+        }
+    }
+
+    fn gilrs_gamepad_state(
+        self: Box<Self>,
+        _id: usize,
+        _gamepad: &::gilrs::Gamepad,
+        _world: &mut World,
+    ) -> Box<GameState> {
+        self
+    }
+
+    fn paused(&self, _world: &World) -> bool {
+        true
+    }
+}
+
 pub struct GlobalMenu {
     menu: ::menu::Menu<GlobalMenuAction>,
 }
@@ -584,27 +713,44 @@ impl GameState for GlobalMenu {
 
 struct NewMapMenu {
     menu: ::menu::Menu<NewMapMenuAction>,
+    /// From 0 to 10
+    difficulty: usize,
 }
 
 impl NewMapMenu {
     pub fn new(_world: &::specs::World) -> Self {
         use self::NewMapMenuAction::*;
+        let difficulty = 5;
         let menu = ::menu::MenuBuilder::new()
+            .add_left_right(format!("Difficulty: {}", difficulty), ReduceDifficulty, IncreaseDifficulty)
             .add_middle("Play".to_string(), Play)
             .build();
 
         NewMapMenu {
             menu,
+            difficulty,
         }
     }
 
-    fn process_action(self: Box<Self>, action: NewMapMenuAction, world: &mut World) -> Box<GameState> {
+    fn process_action(mut self: Box<Self>, action: NewMapMenuAction, world: &mut World) -> Box<GameState> {
         use self::NewMapMenuAction::*;
 
         match action {
+            ReduceDifficulty => {
+                self.difficulty = self.difficulty.saturating_sub(1);
+                self.menu.reset_name(0, format!("Difficulty: {}", self.difficulty));
+                self
+            }
+            IncreaseDifficulty => {
+                self.difficulty = (self.difficulty + 1).min(10);
+                self.menu.reset_name(0, format!("Difficulty: {}", self.difficulty));
+                self
+            }
             Play => {
-                ::level::LevelBuilder {
-                    half_size: 9,
+                let level = ::level::LevelBuilder {
+                    half_size: [9, 9, 9],
+                    shape: ::shape::Shape::Cuboid,
+                    wrap: [false; 3],
                     x_shift: false,
                     y_shift: false,
                     z_shift: false,
@@ -614,8 +760,16 @@ impl NewMapMenu {
                     rocket_launcher: 1,
                     mine: 1,
                     target: 1,
-                }.build(world);
-                Box::new(Game::new()) as Box<GameState>
+                    generator: "kruskal".to_string(),
+                    seed: ::rand::random(),
+                    attempts: 20,
+                    players: 3,
+                    lives: ::CFG.lives,
+                    rules: ::placement::PlacementRules::default(),
+                }.tune(self.difficulty as f64 / 10.0)
+                    .ok_or_show(|e| format!("Failed to generate level: {}", e));
+                level.build(world);
+                Box::new(Game::new(world)) as Box<GameState>
             }
         }
     }
@@ -623,6 +777,8 @@ impl NewMapMenu {
 
 #[derive(Clone, Copy)]
 enum NewMapMenuAction {
+    ReduceDifficulty,
+    IncreaseDifficulty,
     Play,
 }
 
@@ -0,0 +1,221 @@
+use rand::RngCore;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::ops::Mul;
+
+/// Algorithm used by `LevelBuilder` to generate the maze of a level
+///
+/// The returned maze is ready to be tiled: post processing is part of the generator
+pub trait MazeGenerator<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    /// Size must be odd on every axis, corridors are only carved in the cells of the mask
+    ///
+    /// The mask is in the coordinates of `size`, the returned maze keeps it and wraps the
+    /// axes of `wrap`
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        rng: &mut RngCore,
+    ) -> ::maze::Maze<D>;
+}
+
+/// Partial reverse Kruskal followed by room and dead corridor filling
+pub struct Kruskal<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    pub percent: f64,
+    pub bug: ::na::VectorN<isize, D>,
+}
+
+impl<D> MazeGenerator<D> for Kruskal<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        mut rng: &mut RngCore,
+    ) -> ::maze::Maze<D> {
+        let mut maze = ::maze::Maze::new_kruskal(size, self.percent, self.bug.clone(), &mut rng);
+        if let Some(mask) = mask {
+            maze.set_mask(mask);
+        }
+        set_wrap(&mut maze, wrap);
+        maze.reduce(1);
+        maze.circle();
+        maze.fill_smallests();
+        while maze.fill_dead_corridors() {}
+        maze.reduce(1);
+        maze
+    }
+}
+
+/// Perfect maze with long winding corridors and few branches
+pub struct RecursiveBacktracker;
+
+impl<D> MazeGenerator<D> for RecursiveBacktracker
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        mut rng: &mut RngCore,
+    ) -> ::maze::Maze<D> {
+        let mut maze = ::maze::Maze::new_recursive_backtracker(size, mask, &mut rng);
+        set_wrap(&mut maze, wrap);
+        maze.reduce(1);
+        close_mask(&mut maze);
+        maze
+    }
+}
+
+/// Perfect maze with many short dead ends
+pub struct Prim;
+
+impl<D> MazeGenerator<D> for Prim
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        mut rng: &mut RngCore,
+    ) -> ::maze::Maze<D> {
+        let mut maze = ::maze::Maze::new_prim(size, mask, &mut rng);
+        set_wrap(&mut maze, wrap);
+        maze.reduce(1);
+        close_mask(&mut maze);
+        maze
+    }
+}
+
+/// Perfect maze between backtracker (newest = 1.0) and Prim like (newest = 0.0)
+pub struct GrowingTree {
+    pub newest: f64,
+}
+
+impl<D> MazeGenerator<D> for GrowingTree
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        mut rng: &mut RngCore,
+    ) -> ::maze::Maze<D> {
+        let mut maze = ::maze::Maze::new_growing_tree(size, self.newest, mask, &mut rng);
+        set_wrap(&mut maze, wrap);
+        maze.reduce(1);
+        close_mask(&mut maze);
+        maze
+    }
+}
+
+/// Perfect maze with corridors biased along the slices of the last axis
+pub struct Eller;
+
+impl<D> MazeGenerator<D> for Eller
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn generate(
+        &self,
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        wrap: &[bool],
+        mut rng: &mut RngCore,
+    ) -> ::maze::Maze<D> {
+        let mut maze = ::maze::Maze::new_eller(size, mask, &mut rng);
+        set_wrap(&mut maze, wrap);
+        maze.reduce(1);
+        close_mask(&mut maze);
+        maze
+    }
+}
+
+/// Wrapped axes must be set before `circle` so it does not wall their faces
+fn set_wrap<D>(maze: &mut ::maze::Maze<D>, wrap: &[bool])
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    for (axis, &wrap) in wrap.iter().enumerate() {
+        maze.set_wrap(axis, wrap);
+    }
+}
+
+/// Wall the border of the mask of perfect mazes, cells cut off by it are filled
+fn close_mask<D>(maze: &mut ::maze::Maze<D>)
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    if maze.mask().is_some() {
+        maze.circle();
+        maze.fill_smallests();
+    }
+}
+
+/// Names accepted by `from_name`
+pub const NAMES: [&str; 5] = ["kruskal", "recursive_backtracker", "prim", "growing_tree", "eller"];
+
+/// Kruskal parameters are only used by the kruskal generator
+pub fn from_name<D>(
+    name: &str,
+    percent: f64,
+    bug: ::na::VectorN<isize, D>,
+) -> Option<Box<MazeGenerator<D>>>
+where
+    D: ::na::Dim + ::na::DimName + Hash + 'static,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    match name {
+        "kruskal" => Some(Box::new(Kruskal { percent, bug }) as Box<_>),
+        "recursive_backtracker" => Some(Box::new(RecursiveBacktracker) as Box<_>),
+        "prim" => Some(Box::new(Prim) as Box<_>),
+        "growing_tree" => Some(Box::new(GrowingTree { newest: 0.5 }) as Box<_>),
+        "eller" => Some(Box::new(Eller) as Box<_>),
+        _ => None,
+    }
+}
+
+#[test]
+fn perfect_mazes_are_connected() {
+    let size = ::na::Vector3::new(9, 7, 11);
+    for name in NAMES.iter().filter(|&&name| name != "kruskal") {
+        let generator = from_name(name, 0.0, ::na::zero()).unwrap();
+        let mut maze = generator.generate(size, None, &[false; 3], &mut ::util::seeded_rng(0));
+        assert!(!maze.fill_smallests(), "{} maze is not connected", name);
+    }
+}
@@ -0,0 +1,253 @@
+//! Graph of the rooms of a maze, placement zones are computed from it
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Room,
+    /// Room of one cell where corridors meet
+    Junction,
+    /// End of a corridor leading nowhere
+    DeadEnd,
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub cells: HashSet<::na::Vector3<isize>>,
+}
+
+/// Corridor between two nodes
+#[derive(Clone, Debug)]
+pub struct Edge {
+    pub nodes: (usize, usize),
+    pub cells: HashSet<::na::Vector3<isize>>,
+    /// Number of moves from one node to the other
+    pub length: usize,
+}
+
+/// Rooms and junctions linked by corridors
+///
+/// A corridor looping on a node is an edge from the node to itself
+pub struct MazeGraph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    cell_nodes: HashMap<::na::Vector3<isize>, usize>,
+    cell_edges: HashMap<::na::Vector3<isize>, usize>,
+}
+
+impl MazeGraph {
+    pub fn new(maze: &::maze::Maze<::na::U3>) -> Self {
+        let mut nodes = maze.compute_room_zones()
+            .into_iter()
+            .map(|cells| Node {
+                kind: if cells.len() == 1 { NodeKind::Junction } else { NodeKind::Room },
+                cells,
+            })
+            .collect::<Vec<_>>();
+
+        let mut cell_nodes = HashMap::new();
+        for (id, node) in nodes.iter().enumerate() {
+            for cell in &node.cells {
+                cell_nodes.insert(*cell, id);
+            }
+        }
+
+        let mut edges = vec![];
+        for mut corridor in maze.compute_corridor_zones() {
+            // sorted so the graph doesn't depend on hash order
+            let mut cells = corridor.iter().cloned().collect::<Vec<_>>();
+            cells.sort_by(|a, b| a.iter().cmp(b.iter()));
+
+            let mut touched = cells
+                .iter()
+                .flat_map(|cell| maze.free_successors(cell))
+                .filter_map(|(next, _)| cell_nodes.get(&next).cloned())
+                .collect::<Vec<_>>();
+            touched.sort();
+            touched.dedup();
+
+            if touched.len() < 2 {
+                let end = cells
+                    .iter()
+                    .find(|cell| maze.free_successors(cell).len() == 1)
+                    .cloned();
+                match end {
+                    Some(end) if !touched.is_empty() => {
+                        corridor.remove(&end);
+                        let id = nodes.len();
+                        cell_nodes.insert(end, id);
+                        nodes.push(Node {
+                            kind: NodeKind::DeadEnd,
+                            cells: Some(end).into_iter().collect(),
+                        });
+                        touched.push(id);
+                    }
+                    // loop leaving and entering the same node
+                    None if !touched.is_empty() => {
+                        let node = touched[0];
+                        touched.push(node);
+                    }
+                    // corridor touching no node
+                    _ => continue,
+                }
+            }
+
+            for &other in &touched[1..] {
+                edges.push(Edge {
+                    nodes: (touched[0], other),
+                    length: corridor.len() + 1,
+                    cells: corridor.clone(),
+                });
+            }
+        }
+
+        let mut cell_edges = HashMap::new();
+        for (id, edge) in edges.iter().enumerate() {
+            for cell in &edge.cells {
+                cell_edges.insert(*cell, id);
+            }
+        }
+
+        MazeGraph {
+            nodes,
+            edges,
+            cell_nodes,
+            cell_edges,
+        }
+    }
+
+    pub fn node_of(&self, cell: &::na::Vector3<isize>) -> Option<usize> {
+        self.cell_nodes.get(cell).cloned()
+    }
+
+    pub fn edge_of(&self, cell: &::na::Vector3<isize>) -> Option<usize> {
+        self.cell_edges.get(cell).cloned()
+    }
+
+    /// Node of the cell or the first node of its corridor
+    pub fn node_near(&self, cell: &::na::Vector3<isize>) -> Option<usize> {
+        self.node_of(cell)
+            .or_else(|| self.edge_of(cell).map(|edge| self.edges[edge].nodes.0))
+    }
+
+    /// Neighbour nodes and the edges leading to them
+    pub fn neighbours(&self, node: usize) -> Vec<(usize, usize)> {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter_map(|(id, edge)| match edge.nodes {
+                (a, b) if a == node => Some((b, id)),
+                (a, b) if b == node => Some((a, id)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Shortest distance to every node and the previous node on the path
+    fn dijkstra(&self, from: usize) -> (Vec<Option<usize>>, Vec<Option<usize>>) {
+        let mut distances = vec![None; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        let mut heap = BinaryHeap::new();
+        distances[from] = Some(0);
+        heap.push(Reverse((0, from)));
+        while let Some(Reverse((distance, node))) = heap.pop() {
+            if distances[node].map_or(false, |d| d < distance) {
+                continue;
+            }
+            for (next, edge) in self.neighbours(node) {
+                let next_distance = distance + self.edges[edge].length;
+                if distances[next].map_or(true, |d| next_distance < d) {
+                    distances[next] = Some(next_distance);
+                    previous[next] = Some(node);
+                    heap.push(Reverse((next_distance, next)));
+                }
+            }
+        }
+        (distances, previous)
+    }
+
+    /// Length of the shortest path to every node, None if unreachable
+    pub fn distances_from(&self, from: usize) -> Vec<Option<usize>> {
+        self.dijkstra(from).0
+    }
+
+    /// Farthest reachable room, junctions and dead ends are ignored
+    pub fn farthest_room_from(&self, from: usize) -> Option<usize> {
+        self.distances_from(from)
+            .into_iter()
+            .enumerate()
+            .filter(|&(id, _)| self.nodes[id].kind == NodeKind::Room)
+            .filter_map(|(id, distance)| distance.map(|d| (id, d)))
+            .max_by_key(|&(_, d)| d)
+            .map(|(id, _)| id)
+    }
+
+    /// Nodes of the shortest path, both ends included
+    pub fn critical_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let (distances, previous) = self.dijkstra(from);
+        distances[to]?;
+        let mut path = vec![to];
+        while let Some(node) = previous[*path.last().unwrap()] {
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Rooms with only one way in
+    pub fn dead_end_rooms(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].kind == NodeKind::Room && self.neighbours(id).len() <= 1)
+            .collect()
+    }
+}
+
+#[test]
+fn two_rooms_and_a_dead_end() {
+    // rooms a and b joined by a corridor, a dead end leaves b
+    let free = [
+        (1, 1), (2, 1), (1, 2), (2, 2), (1, 3), (2, 3),
+        (3, 2), (4, 2),
+        (5, 1), (6, 1), (7, 1), (5, 2), (6, 2), (7, 2), (5, 3), (6, 3), (7, 3),
+        (6, 4), (6, 5),
+    ];
+    let maze = ::maze::walled(::na::Vector3::new(9, 7, 3), |c| c[2] == 1 && free.contains(&(c[0], c[1])));
+
+    let graph = MazeGraph::new(&maze);
+    let a = graph.node_of(&::na::Vector3::new(1, 1, 1)).unwrap();
+    let b = graph.node_of(&::na::Vector3::new(7, 3, 1)).unwrap();
+    let dead_end = graph.node_of(&::na::Vector3::new(6, 5, 1)).unwrap();
+    assert_eq!(graph.nodes.len(), 3);
+    assert_eq!(graph.nodes[dead_end].kind, NodeKind::DeadEnd);
+    assert_eq!(graph.edges.len(), 2);
+
+    assert_eq!(graph.farthest_room_from(a), Some(b));
+    assert_eq!(graph.critical_path(a, dead_end), Some(vec![a, b, dead_end]));
+    assert_eq!(graph.dead_end_rooms(), vec![a]);
+    assert_eq!(graph.distances_from(a)[dead_end], Some(5));
+    assert_eq!(graph.node_near(&::na::Vector3::new(3, 2, 1)), Some(a));
+}
+
+#[test]
+fn loop_corridor_is_a_self_loop() {
+    // a corridor leaves the room on the right and comes back from below
+    let free = [
+        (1, 1), (2, 1), (1, 2), (2, 2), (1, 3), (2, 3),
+        (3, 1), (4, 1), (5, 1), (5, 2), (5, 3), (5, 4),
+        (5, 5), (4, 5), (3, 5), (2, 5), (1, 5), (1, 4),
+    ];
+    let maze = ::maze::walled(::na::Vector3::new(7, 7, 3), |c| c[2] == 1 && free.contains(&(c[0], c[1])));
+
+    let graph = MazeGraph::new(&maze);
+    let room = graph.node_of(&::na::Vector3::new(1, 1, 1)).unwrap();
+    assert_eq!(graph.nodes.len(), 1);
+    assert_eq!(graph.edges.len(), 1);
+    assert_eq!(graph.edges[0].nodes, (room, room));
+    assert_eq!(graph.edges[0].length, 13);
+    assert_eq!(graph.edge_of(&::na::Vector3::new(5, 3, 1)), Some(0));
+    assert_eq!(graph.neighbours(room), vec![(room, 0)]);
+}
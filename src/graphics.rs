@@ -20,7 +20,6 @@ use vulkano::image::ImageLayout;
 use vulkano::format::{ClearValue, Format};
 use vulkano;
 use alga::general::SubsetOf;
-use rand::{thread_rng, Rng};
 use rand::distributions::{Distribution, Range};
 use std::sync::Arc;
 use std::time::Duration;
@@ -56,34 +55,6 @@ impl Vertex {
             tex_coords,
         }
     }
-
-    pub fn from_obj(obj: String) -> Vec<Vertex> {
-        // TODO: tex coords
-        let ref obj = ::wavefront_obj::obj::parse(obj).unwrap().objects[0];
-        let range = Range::new(0f32, 1f32);
-        obj.geometry
-            .iter()
-            .flat_map(|geometry| {
-                geometry
-                    .shapes
-                    .iter()
-                    .flat_map(|shape| match shape.primitive {
-                        ::wavefront_obj::obj::Primitive::Triangle(a, b, c) => vec![a, b, c],
-                        _ => unimplemented!(),
-                    })
-                    .map(|(vertex_index, _, _)| {
-                        let v = obj.vertices[vertex_index];
-                        Vertex {
-                            position: [v.x as f32, v.y as f32, v.z as f32],
-                            tex_coords: [
-                                range.sample(&mut thread_rng()),
-                                range.sample(&mut thread_rng()),
-                            ],
-                        }
-                    })
-            })
-            .collect()
-    }
 }
 
 pub struct Graphics {
@@ -111,7 +82,7 @@ pub struct Graphics {
     pub need_update_glyph_cache: bool,
 
     // TODO: maybe use an array
-    pub tile_assets: HashMap<::tile::TileSize, (Arc<DescriptorSet + Send + Sync + 'static>, Arc<ImmutableBuffer<[Vertex]>>, [f32; 3])>,
+    pub tile_assets: HashMap<::tile::TileSize, (Arc<DescriptorSet + Send + Sync + 'static>, Arc<ImmutableBuffer<[Vertex]>>)>,
     pub tube_assets: HashMap<::tube::Shape, (Arc<DescriptorSet + Send + Sync + 'static>, Arc<ImmutableBuffer<[Vertex]>>)>,
 
     cache: ::rusttype::gpu_cache::Cache<'static>,
@@ -391,6 +362,9 @@ impl Graphics {
             queue.clone(),
         ).unwrap();
 
+        // textures don't depend on the level so they are the same on every run
+        let mut rng = ::util::seeded_rng(0);
+
         let (ball_vertex_buffer, _future) = ImmutableBuffer::from_iter(
             {
                 let sphere = ::ncollide::procedural::sphere(1.0, 32, 32, false);
@@ -424,8 +398,8 @@ impl Graphics {
                 .map(|v| {
                     let range = Range::new(0f32, 1f32);
                     let tex_coords = [
-                        range.sample(&mut thread_rng()),
-                        range.sample(&mut thread_rng()),
+                        range.sample(&mut rng),
+                        range.sample(&mut rng),
                     ];
                     Vertex::new(v, tex_coords)
                 }),
@@ -453,6 +427,7 @@ impl Graphics {
                 ::CFG.unlocal_texture_layers,
                 texture_generation_filter,
                 false,
+                &mut rng,
             );
 
             ImmutableImage::from_iter(
@@ -465,10 +440,12 @@ impl Graphics {
 
         let mut tile_assets = HashMap::new();
         let mut _futures = (vec![], vec![]);
-        for tile_size in ::tile::TileSize::iter_variants() {
+        for tile_size in ::tile::TileSize::all(::CFG.tile_max_size) {
+            // same resolution for every tile
+            let subdivision = ::CFG.tile_subdivision as u32;
             let dimensions = Dimensions::Dim2d {
-                width: ::CFG.unlocal_texture_size * tile_size.width() as u32,
-                height: ::CFG.unlocal_texture_size * tile_size.height() as u32,
+                width: (::CFG.unlocal_texture_size * tile_size.width() as u32 / subdivision).max(1),
+                height: (::CFG.unlocal_texture_size * tile_size.height() as u32 / subdivision).max(1),
             };
 
             let image = ::texture::generate_texture(
@@ -477,6 +454,7 @@ impl Graphics {
                 ::CFG.unlocal_texture_layers,
                 texture_generation_filter,
                 false,
+                &mut rng,
             );
 
             let (texture, future) = ImmutableImage::from_iter(
@@ -496,7 +474,7 @@ impl Graphics {
             let texture_descriptor_set = Arc::new(texture_descriptor_set) as Arc<_>;
 
             let (vertex_buffer, future) = ImmutableBuffer::from_iter(
-                ::obj::generate_tile(tile_size.width(), tile_size.height())
+                ::obj::generate_tile(tile_size, ::CFG.tile_subdivision)
                     .iter()
                     .cloned(),
                 BufferUsage::vertex_buffer(),
@@ -507,7 +485,7 @@ impl Graphics {
 
             tile_assets.insert(
                 tile_size.clone(),
-                (texture_descriptor_set, vertex_buffer, [0.0; 3]),
+                (texture_descriptor_set, vertex_buffer),
             );
         }
 
@@ -525,6 +503,7 @@ impl Graphics {
                 ::CFG.unlocal_texture_layers,
                 texture_generation_filter,
                 false,
+                &mut rng,
             );
 
             let (texture, future) = ImmutableImage::from_iter(
@@ -544,7 +523,7 @@ impl Graphics {
             let texture_descriptor_set = Arc::new(texture_descriptor_set) as Arc<_>;
 
             let (vertex_buffer, future) = ImmutableBuffer::from_iter(
-                Vertex::from_obj(shape.obj()).iter().cloned(),
+                ::obj::generate_tube(shape).iter().cloned(),
                 BufferUsage::vertex_buffer(),
                 queue.clone(),
             ).unwrap();
@@ -574,7 +553,7 @@ impl Graphics {
             swapchain.dimensions(),
         );
 
-        let mut graphics = Graphics {
+        let graphics = Graphics {
             cache,
             cache_pixel_buffer,
             cache_image_set,
@@ -604,19 +583,9 @@ impl Graphics {
             tube_assets,
         };
 
-        graphics.reset_colors();
-
         graphics
     }
 
-    pub fn reset_colors(&mut self) {
-        let mut colors = ::colors::GenPale::colors();
-        thread_rng().shuffle(&mut colors);
-        for tile in self.tile_assets.values_mut() {
-            tile.2 = colors.pop().unwrap().into();
-        }
-    }
-
     fn recreate(&mut self, window: &Arc<Surface<::winit::Window>>) {
         let mut remaining_try = 20;
         let recreate = loop {
@@ -951,8 +920,8 @@ impl Graphics {
                 );
 
                 for tile in &world.read_resource::<::resource::Tiles>().0 {
-                    let (ref texture_descriptor_set, ref vertex_buffer, color) =
-                        self.tile_assets[&tile.size];
+                    let (ref texture_descriptor_set, ref vertex_buffer) = self.tile_assets[&tile.size];
+                    let color: [f32; 3] = tile.color.into();
 
                     let position: ::na::Transform3<f32> = tile.position.to_superset();
 
@@ -0,0 +1,297 @@
+use hibitset::{BitIter, BitSet, BitSetLike};
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::Hash;
+use std::ops::Mul;
+
+/// Dense set of the cells of a box, much faster than hashing cells
+///
+/// Cells are indexed in grid order, the first axis varying the slowest, and iterated in this
+/// order
+pub struct Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    size: ::na::VectorN<isize, D>,
+    bits: BitSet,
+    len: usize,
+}
+
+impl<D> Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    pub fn new(size: ::na::VectorN<isize, D>) -> Self {
+        let grid = Grid {
+            size,
+            bits: BitSet::new(),
+            len: 0,
+        };
+        assert!(grid.cell_count() <= u32::max_value() as usize);
+        grid
+    }
+
+    /// Cells outside of the box are ignored
+    pub fn from_cells<I>(size: ::na::VectorN<isize, D>, cells: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Borrow<::na::VectorN<isize, D>>,
+    {
+        let mut grid = Grid::new(size);
+        for cell in cells {
+            if grid.index(cell.borrow()).is_some() {
+                grid.insert(cell.borrow());
+            }
+        }
+        grid
+    }
+
+    pub fn size(&self) -> ::na::VectorN<isize, D> {
+        self.size.clone()
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.size.iter().map(|&s| s.max(0) as usize).product()
+    }
+
+    /// Number of cells in the grid
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// None if the cell is outside of the box
+    pub fn index(&self, cell: &::na::VectorN<isize, D>) -> Option<u32> {
+        let mut index = 0;
+        for i in 0..D::dim() {
+            if cell[i] < 0 || cell[i] >= self.size[i] {
+                return None;
+            }
+            index = index * self.size[i] + cell[i];
+        }
+        Some(index as u32)
+    }
+
+    pub fn cell(&self, index: u32) -> ::na::VectorN<isize, D> {
+        let mut index = index as isize;
+        let mut cell = ::na::VectorN::<isize, D>::zeros();
+        for i in (0..D::dim()).rev() {
+            cell[i] = index % self.size[i];
+            index /= self.size[i];
+        }
+        cell
+    }
+
+    pub fn contains(&self, cell: &::na::VectorN<isize, D>) -> bool {
+        self.index(cell).map_or(false, |index| self.bits.contains(index))
+    }
+
+    /// Return whereas the cell was not already in the grid
+    ///
+    /// Panics if the cell is outside of the box
+    pub fn insert(&mut self, cell: &::na::VectorN<isize, D>) -> bool {
+        let index = self.index(cell).expect("cell outside of the grid");
+        let added = !self.bits.add(index);
+        if added {
+            self.len += 1;
+        }
+        added
+    }
+
+    /// Return whereas the cell was in the grid
+    pub fn remove(&mut self, cell: &::na::VectorN<isize, D>) -> bool {
+        let removed = self.index(cell).map_or(false, |index| self.bits.remove(index));
+        if removed {
+            self.len -= 1;
+        }
+        removed
+    }
+
+    /// Cells of the grid in grid order
+    pub fn iter(&self) -> Cells<D> {
+        Cells {
+            grid: self,
+            indices: (&self.bits).iter(),
+        }
+    }
+
+    /// Keep only the cells satisfying the predicate
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&::na::VectorN<isize, D>) -> bool,
+    {
+        let removed = self.iter().filter(|cell| !f(cell)).collect::<Vec<_>>();
+        for cell in &removed {
+            self.remove(cell);
+        }
+    }
+
+    /// Panics if a cell is outside of the box
+    pub fn extend<I>(&mut self, cells: I)
+    where
+        I: IntoIterator,
+        I::Item: Borrow<::na::VectorN<isize, D>>,
+    {
+        for cell in cells {
+            self.insert(cell.borrow());
+        }
+    }
+}
+
+/// Iterator over the cells of a grid, see `Grid::iter`
+pub struct Cells<'a, D>
+where
+    D: ::na::Dim + ::na::DimName + Hash + 'a,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    grid: &'a Grid<D>,
+    indices: BitIter<&'a BitSet>,
+}
+
+impl<'a, D> Iterator for Cells<'a, D>
+where
+    D: ::na::Dim + ::na::DimName + Hash + 'a,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    type Item = ::na::VectorN<isize, D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|index| self.grid.cell(index))
+    }
+}
+
+impl<'a, D> IntoIterator for &'a Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash + 'a,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    type Item = ::na::VectorN<isize, D>;
+    type IntoIter = Cells<'a, D>;
+
+    fn into_iter(self) -> Cells<'a, D> {
+        self.iter()
+    }
+}
+
+impl<D> Clone for Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn clone(&self) -> Self {
+        Grid {
+            size: self.size.clone(),
+            bits: self.bits.clone(),
+            len: self.len,
+        }
+    }
+}
+
+impl<D> PartialEq for Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<D> fmt::Debug for Grid<D>
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set()
+            .entries(self.iter().map(|cell| cell.iter().cloned().collect::<Vec<_>>()))
+            .finish()
+    }
+}
+
+/// Disjoint sets of indices with path compression and union by rank
+pub struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+}
+
+impl UnionFind {
+    pub fn new(len: usize) -> Self {
+        UnionFind {
+            parents: (0..len).collect(),
+            ranks: vec![0; len],
+        }
+    }
+
+    pub fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut index = index;
+        while self.parents[index] != root {
+            let next = self.parents[index];
+            self.parents[index] = root;
+            index = next;
+        }
+        root
+    }
+
+    /// Return whereas the sets were disjoint
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a == b {
+            return false;
+        }
+        if self.ranks[a] < self.ranks[b] {
+            self.parents[a] = b;
+        } else {
+            self.parents[b] = a;
+            if self.ranks[a] == self.ranks[b] {
+                self.ranks[a] += 1;
+            }
+        }
+        true
+    }
+}
+
+#[test]
+fn grid_index_round_trip() {
+    let grid = Grid::new(::na::Vector3::new(3, 4, 5));
+    for index in 0..grid.cell_count() as u32 {
+        assert_eq!(grid.index(&grid.cell(index)), Some(index));
+    }
+    assert_eq!(grid.index(&::na::Vector3::new(3, 0, 0)), None);
+}
+
+#[test]
+fn grid_set_operations() {
+    let size = ::na::Vector3::new(3, 4, 5);
+    let cells = [::na::Vector3::new(2, 0, 1), ::na::Vector3::new(0, 3, 4), ::na::Vector3::new(5, 0, 0)];
+    let mut grid = Grid::from_cells(size, cells.iter());
+    assert_eq!(grid.len(), 2);
+    assert_eq!(grid.iter().collect::<Vec<_>>(), vec![cells[1], cells[0]]);
+
+    assert!(!grid.insert(&cells[0]));
+    assert!(grid.remove(&cells[0]));
+    assert!(!grid.remove(&cells[2]));
+    assert_eq!(grid.len(), 1);
+
+    grid.extend(vec![::na::Vector3::new(1, 1, 1), ::na::Vector3::new(0, 0, 0)]);
+    grid.retain(|cell| cell[0] > 0);
+    assert_eq!(grid, Grid::from_cells(size, vec![::na::Vector3::new(1, 1, 1)]));
+}
@@ -1,5 +1,18 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+/// Cells beyond the faces of wrapped axes where the opposite side of the level is copied
+const SEAM_MARGIN: isize = 2;
+
+#[derive(Clone)]
 pub struct LevelBuilder {
-    pub half_size: usize,
+    /// Half size on each axis
+    pub half_size: [usize; 3],
+    pub shape: ::shape::Shape,
+    /// Axes whose opposite faces are joined
+    pub wrap: [bool; 3],
     pub x_shift: bool,
     pub y_shift: bool,
     pub z_shift: bool,
@@ -9,18 +22,56 @@ pub struct LevelBuilder {
     pub mine: usize,
     pub rocket_launcher: usize,
     pub target: usize,
+    /// Name of the maze generator, see `generator::NAMES`
+    pub generator: String,
+    /// Same seed and parameters always give the same level
+    pub seed: u64,
+    /// Number of generations tried before giving up on invalid levels
+    pub attempts: usize,
+    /// Number of spawns
+    pub players: usize,
+    /// Lives of each player
+    pub lives: usize,
+    pub rules: ::placement::PlacementRules,
 }
 
 impl LevelBuilder {
-    pub fn build(&self, world: &mut ::specs::World) {
-        world.maintain();
-        world.delete_all();
+    pub fn build(&self, world: &mut ::specs::World) -> Result<(), GenerationError> {
+        self.describe()?.build(world);
+        Ok(())
+    }
 
+    /// Generate a valid level without loading it in a world
+    pub fn describe(&self) -> Result<LevelDescription, GenerationError> {
+        assert!(self.attempts > 0);
+        let mut rng = ::util::seeded_rng(self.seed);
+        let mut last_error = None;
+        for attempt in 0..self.attempts {
+            match self.generate(&mut rng).and_then(|level| level.validate().map(|()| level)) {
+                Ok(level) => return Ok(level),
+                // no other attempt can do better
+                Err(error @ ValidationError::UnknownGenerator { .. })
+                | Err(error @ ValidationError::HeightmapSize { .. }) => {
+                    return Err(GenerationError {
+                        attempts: attempt + 1,
+                        last_error: error,
+                    })
+                }
+                Err(error) => last_error = Some(error),
+            }
+        }
+        Err(GenerationError {
+            attempts: self.attempts,
+            last_error: last_error.unwrap(),
+        })
+    }
+
+    fn generate(&self, rng: &mut ::rand::prng::ChaChaRng) -> Result<LevelDescription, ValidationError> {
         let mut maze = {
             let size = ::na::Vector3::new(
-                (self.half_size * 2 + 1) as isize,
-                (self.half_size * 2 + 1) as isize,
-                (self.half_size * 2 + 1) as isize,
+                (self.half_size[0] * 2 + 1) as isize,
+                (self.half_size[1] * 2 + 1) as isize,
+                (self.half_size[2] * 2 + 1) as isize,
             );
             let bug = ::na::Vector3::new(
                 if self.x_shift { 1 } else { 0 },
@@ -28,56 +79,833 @@ impl LevelBuilder {
                 if self.z_shift { 1 } else { 0 },
             );
 
-            let mut maze = ::maze::Maze::new_kruskal(size, self.percent, bug);
-            maze.reduce(1);
-            maze.circle();
-            maze.fill_smallests();
-            while maze.fill_dead_corridors() {}
-            maze.reduce(1);
-            maze
+            let generator = ::generator::from_name(&self.generator, self.percent, bug)
+                .ok_or_else(|| ValidationError::UnknownGenerator {
+                    name: self.generator.clone(),
+                })?;
+            self.shape.check()?;
+            generator.generate(size, self.shape.mask(size), &self.wrap, rng)
         };
 
-        let colors = maze.build_colors();
-        for (wall, color) in colors {
-            ::entity::create_wall(::util::to_world(&wall, self.unit), color, world);
+        let size = maze.size();
+        let mut walls = maze.walls.iter().map(|wall| to_array(&wall)).collect::<Vec<_>>();
+        // keep saved files stable
+        walls.sort();
+        let mask = maze.mask().map(|mask| {
+            let mut mask = mask.iter().map(to_array).collect::<Vec<_>>();
+            mask.sort();
+            mask
+        });
+
+        // Tube cells are inserted in maze walls so entities are not placed on them
+        let tubes: Vec<Vec<_>> = ::tube::generate_paths(self.columns, &mut maze, rng)
+            .iter()
+            .map(|path| path.iter().map(to_array).collect())
+            .collect();
+
+        let mut zones = ::placement::GraphZones::new(&maze);
+        let mut spawns = vec![];
+        for _ in 0..self.players {
+            let spawn = ::placement::place(&mut maze, &self.rules.spawn, None, &zones, &spawns, rng)
+                .map_err(|constraint| ValidationError::Unplaceable {
+                    entity: "spawn".to_string(),
+                    constraint,
+                })?;
+            spawns.push(spawn);
+        }
+        let spawn_distances = ::placement::SpawnDistances::new(&maze, &spawns);
+        zones.set_spawns(&spawns);
+
+        let mut hazards = vec![];
+        let mut entities = vec![];
+        for &(kind, number) in [
+            (EntityKind::Mine, self.mine),
+            (EntityKind::Target, self.target),
+            (EntityKind::RocketLauncher, self.rocket_launcher),
+        ].iter()
+        {
+            for _ in 0..number {
+                let rule = self.rules.get(kind);
+                let pos = ::placement::place(
+                    &mut maze,
+                    rule,
+                    Some(&spawn_distances),
+                    &zones,
+                    &hazards,
+                    rng,
+                ).map_err(|constraint| ValidationError::Unplaceable {
+                    entity: format!("{:?}", kind),
+                    constraint,
+                })?;
+                if kind != EntityKind::Target {
+                    hazards.push(pos);
+                }
+                entities.push(EntityPlacement {
+                    kind,
+                    position: to_array(&pos),
+                });
+            }
+        }
+
+        Ok(LevelDescription {
+            size: to_array(&size),
+            unit: self.unit,
+            seed: self.seed,
+            walls,
+            mask,
+            tubes,
+            entities,
+            spawns: spawns.iter().map(to_array).collect(),
+            wrap: self.wrap,
+            lives: self.lives,
+        })
+    }
+
+    /// Change maze size, rooms, tubes and enemies until the level difficulty is close to
+    /// `difficulty` (between 0.0 and 1.0)
+    ///
+    /// Return the closest level generated, `self` is left with the parameters generating it
+    pub fn tune(&mut self, difficulty: f64) -> Result<LevelDescription, GenerationError> {
+        const TOLERANCE: f64 = 0.05;
+        const MAX_TUNING: usize = 10;
+
+        let mut previous_direction = 0;
+        let mut previous: Option<(LevelBuilder, LevelDescription)> = None;
+        let mut level = self.describe()?;
+        for _ in 0..MAX_TUNING {
+            let error = level.difficulty() - difficulty;
+            if error.abs() <= TOLERANCE {
+                break;
+            }
+            let direction = if error < 0.0 { 1 } else { -1 };
+            if direction == -previous_direction {
+                // overshot the target, keep the closest of the last two levels
+                if let Some((builder, previous_level)) = previous {
+                    if (previous_level.difficulty() - difficulty).abs() < error.abs() {
+                        *self = builder;
+                        level = previous_level;
+                    }
+                }
+                break;
+            }
+            previous_direction = direction;
+            previous = Some((self.clone(), level));
+
+            for half_size in &mut self.half_size {
+                *half_size = clamp(*half_size as isize + direction, 3, 15) as usize;
+            }
+            self.percent = (self.percent - direction as f64).max(0.0).min(20.0);
+            self.columns = clamp(self.columns as isize - direction, 0, 5) as usize;
+            self.mine = clamp(self.mine as isize + direction, 0, 10) as usize;
+            self.rocket_launcher = clamp(self.rocket_launcher as isize + direction, 0, 5) as usize;
+            level = self.describe()?;
+        }
+        Ok(level)
+    }
+
+    /// Generate the level and save it
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<LevelDescription, ::failure::Error> {
+        let level = self.describe()?;
+        level.save_to_file(path)?;
+        Ok(level)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<LevelDescription, ::failure::Error> {
+        LevelDescription::from_file(path)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntityKind {
+    Mine,
+    Target,
+    RocketLauncher,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityPlacement {
+    pub kind: EntityKind,
+    pub position: [isize; 3],
+}
+
+/// Complete level, all positions are maze cells
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LevelDescription {
+    pub size: [isize; 3],
+    /// Size of a maze cell in the world
+    pub unit: f32,
+    /// Used for tiling and coloring
+    pub seed: u64,
+    pub walls: Vec<[isize; 3]>,
+    /// Cells inside the shape of the level, all cells if none
+    #[serde(default)]
+    pub mask: Option<Vec<[isize; 3]>>,
+    /// Paths as returned by `tube::generate_paths`
+    pub tubes: Vec<Vec<[isize; 3]>>,
+    pub entities: Vec<EntityPlacement>,
+    /// Spawn of each player
+    pub spawns: Vec<[isize; 3]>,
+    /// Axes whose opposite faces are joined
+    #[serde(default)]
+    pub wrap: [bool; 3],
+    /// Lives of each player
+    #[serde(default = "default_lives")]
+    pub lives: usize,
+}
+
+fn default_lives() -> usize {
+    ::CFG.lives
+}
+
+impl LevelDescription {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ::failure::Error> {
+        let file = File::open(path)?;
+        Ok(::ron::de::from_reader(file)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ::failure::Error> {
+        let string = ::ron::ser::to_string_pretty(&self, ::ron::ser::PrettyConfig::default())?;
+        let mut file = File::create(path)?;
+        file.write_all(string.as_bytes())?;
+        Ok(())
+    }
+
+    /// Level from the layered text format of `Maze::from_layers`, without tubes
+    pub fn from_layers(text: &str, unit: f32, seed: u64) -> Result<Self, ::failure::Error> {
+        let (maze, markers) = ::maze::Maze::from_layers(text)?;
+        Ok(LevelDescription::from_maze(&maze, &markers, vec![], unit, seed))
+    }
+
+    /// Level from the first model of a MagicaVoxel file, tubes join its wall parts
+    pub fn from_vox<P: AsRef<Path>>(
+        path: P,
+        markers: &::vox::VoxMarkers,
+        unit: f32,
+        seed: u64,
+    ) -> Result<Self, ::failure::Error> {
+        let (maze, marked) = ::vox::VoxModel::from_file(path)?.to_maze(markers);
+        let tubes = ::tube::generate_paths(0, &mut maze.clone(), &mut ::util::seeded_rng(seed))
+            .iter()
+            .map(|path| path.iter().map(to_array).collect())
+            .collect();
+        Ok(LevelDescription::from_maze(&maze, &marked, tubes, unit, seed))
+    }
+
+    fn from_maze(
+        maze: &::maze::Maze<::na::U3>,
+        markers: &[(::maze::Marker, ::na::Vector3<isize>)],
+        tubes: Vec<Vec<[isize; 3]>>,
+        unit: f32,
+        seed: u64,
+    ) -> Self {
+        let mut walls = maze.walls.iter().map(|wall| to_array(&wall)).collect::<Vec<_>>();
+        walls.sort();
+
+        let mut entities = vec![];
+        let mut spawns = vec![];
+        for &(marker, cell) in markers {
+            let kind = match marker {
+                ::maze::Marker::Spawn => {
+                    spawns.push(to_array(&cell));
+                    continue;
+                }
+                ::maze::Marker::Target => EntityKind::Target,
+                ::maze::Marker::Mine => EntityKind::Mine,
+                ::maze::Marker::RocketLauncher => EntityKind::RocketLauncher,
+            };
+            entities.push(EntityPlacement {
+                kind,
+                position: to_array(&cell),
+            });
+        }
+
+        LevelDescription {
+            size: to_array(&maze.size()),
+            unit,
+            seed,
+            walls,
+            mask: None,
+            tubes,
+            entities,
+            spawns,
+            wrap: [false; 3],
+            lives: ::CFG.lives,
+        }
+    }
+
+    /// Tubes are not part of the layered text format
+    pub fn to_layers(&self) -> String {
+        let markers = self.spawns
+            .iter()
+            .map(|spawn| (::maze::Marker::Spawn, from_array(spawn)))
+            .chain(self.entities.iter().map(|entity| {
+                let marker = match entity.kind {
+                    EntityKind::Target => ::maze::Marker::Target,
+                    EntityKind::Mine => ::maze::Marker::Mine,
+                    EntityKind::RocketLauncher => ::maze::Marker::RocketLauncher,
+                };
+                (marker, from_array(&entity.position))
+            }))
+            .collect::<Vec<_>>();
+        self.maze().to_layers(&markers)
+    }
+
+    pub fn maze(&self) -> ::maze::Maze<::na::U3> {
+        let mut maze = ::maze::Maze::new_rectangle(from_array(&self.size));
+        maze.walls = ::grid::Grid::from_cells(maze.size(), self.walls.iter().map(from_array));
+        if let Some(ref mask) = self.mask {
+            maze.set_mask(mask.iter().map(from_array).collect());
+        }
+        for (axis, &wrap) in self.wrap.iter().enumerate() {
+            maze.set_wrap(axis, wrap);
+        }
+        maze
+    }
+
+    /// Maze whose tube cells are walls: only riders go through tubes
+    ///
+    /// Tubes may leave the box of the maze, cells outside of it are not walls
+    pub fn navigation_maze(&self) -> ::maze::Maze<::na::U3> {
+        let mut maze = self.maze();
+        let cells = self.paths()
+            .into_iter()
+            .flat_map(|path| path)
+            .filter(|cell| maze.is_inside(cell))
+            .collect::<Vec<_>>();
+        maze.walls.extend(cells);
+        maze
+    }
+
+    pub fn metrics(&self) -> ::metrics::MazeMetrics {
+        let targets = self.entities
+            .iter()
+            .filter(|e| e.kind == EntityKind::Target)
+            .map(|e| from_array(&e.position))
+            .collect::<Vec<_>>();
+        let spawn = self.spawns.first().map(from_array).unwrap_or(::na::zero());
+        ::metrics::MazeMetrics::compute(&self.maze(), spawn, &targets)
+    }
+
+    /// Maze difficulty weighted with the number of enemies, between 0.0 and 1.0
+    pub fn difficulty(&self) -> f64 {
+        let enemies = self.entities
+            .iter()
+            .filter(|e| e.kind != EntityKind::Target)
+            .count();
+        0.8 * self.metrics().difficulty() + 0.2 * (enemies as f64 / 10.0).min(1.0)
+    }
+
+    /// Check the ball fits in the corridors and every target can be reached from every spawn
+    ///
+    /// Paths go through the navigation maze, the one homing entities follow
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if 2.0 * ::CFG.ball_radius >= self.unit {
+            return Err(ValidationError::BallTooLarge);
         }
+        let first_spawn = self.spawns.first().ok_or(ValidationError::NoSpawn)?;
 
-        let mut tiles = ::tile::build_maze(&maze);
+        let mut maze = self.navigation_maze();
+        for spawn in &self.spawns {
+            if maze.walls.contains(&from_array(spawn)) {
+                return Err(ValidationError::SpawnInWall { spawn: *spawn });
+            }
+        }
+
+        // Players can go around the maze
+        let shift = maze.extend(1);
+
+        // Reachability is symmetric: checking from the first spawn is enough
+        let start = from_array(first_spawn) + shift;
+        for spawn in &self.spawns[1..] {
+            if maze.find_path(start, from_array(spawn) + shift).is_none() {
+                return Err(ValidationError::UnreachableSpawn {
+                    from: *first_spawn,
+                    spawn: *spawn,
+                });
+            }
+        }
+        for entity in self.entities.iter().filter(|e| e.kind == EntityKind::Target) {
+            if maze.find_path(start, from_array(&entity.position) + shift).is_none() {
+                return Err(ValidationError::UnreachableTarget {
+                    spawn: *first_spawn,
+                    target: entity.position,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Tiles covering the walls, in world coordinates
+    pub fn tiles(&self) -> Vec<::tile::Tile> {
+        let mut rng = ::util::seeded_rng(self.seed);
+        let maze = self.maze();
+
+        let palette = ::colors::GenPale::colors();
+        let colors = maze.build_colors(palette.len(), &mut rng)
+            .into_iter()
+            .map(|(wall, color)| (wall, palette[color]))
+            .collect();
+        let tiling = ::tile::Tiling::from_configuration();
+        let mut tiles = ::tile::build_maze(&maze, &colors, &tiling, &mut rng);
         for tile in &mut tiles {
             tile.position.translation.vector *= self.unit;
             tile.width *= self.unit;
             tile.height *= self.unit;
         }
-        world.add_resource(::resource::Tiles(tiles));
+        tiles
+    }
 
-        let mut tubes = ::tube::build_tubes(self.columns, &mut maze);
+    fn paths(&self) -> Vec<Vec<::na::Vector3<isize>>> {
+        self.tubes
+            .iter()
+            .map(|path| path.iter().map(from_array).collect())
+            .collect()
+    }
+
+    /// Tube pieces, in world coordinates
+    pub fn tube_pieces(&self) -> Vec<::tube::Tube> {
+        let mut tubes = ::tube::build_tubes(&self.paths());
         for tube in &mut tubes {
             tube.position.translation.vector *= self.unit;
+        }
+        tubes
+    }
+
+    /// Translations to the copies of the level along wrapped axes, in cells, the level itself
+    /// is the zero translation
+    fn seam_offsets(&self) -> Vec<::na::Vector3<isize>> {
+        let mut offsets = vec![::na::zero()];
+        for i in (0..3).filter(|&i| self.wrap[i]) {
+            let size = self.size[i];
+            offsets = offsets
+                .iter()
+                .flat_map(|&offset| {
+                    (-1..2).map(move |k| {
+                        let mut offset: ::na::Vector3<isize> = offset;
+                        offset[i] = k * size;
+                        offset
+                    })
+                })
+                .collect();
+        }
+        offsets
+    }
+
+    /// Whereas the box, in world coordinates, is in the level or near its faces
+    fn near_level(&self, min: &::na::Vector3<f32>, max: &::na::Vector3<f32>) -> bool {
+        let margin = SEAM_MARGIN as f32 * self.unit;
+        (0..3).all(|i| max[i] > -margin && min[i] < self.size[i] as f32 * self.unit + margin)
+    }
+
+    /// Position and half extents of wall colliders, walls near the faces of wrapped axes are
+    /// copied from the opposite side
+    pub fn wall_colliders(&self) -> Vec<(::na::Vector3<f32>, ::na::Vector3<f32>)> {
+        let offsets = self.seam_offsets();
+        let mut colliders = vec![];
+        for (start, size) in self.maze().wall_cuboids() {
+            let size = size.map(|s| s as f32) * self.unit;
+            for offset in &offsets {
+                let min = (start + offset).map(|s| s as f32) * self.unit;
+                if self.near_level(&min, &(min + size)) {
+                    colliders.push((min + size / 2.0, size / 2.0));
+                }
+            }
+        }
+        colliders
+    }
+
+    /// Tiles and their copies near the faces of wrapped axes, in world coordinates
+    fn seam_tiles(&self) -> Vec<::tile::Tile> {
+        let tiles = self.tiles();
+        let mut copies = vec![];
+        for offset in self.seam_offsets() {
+            if offset == ::na::zero() {
+                continue;
+            }
+            let offset = offset.map(|c| c as f32) * self.unit;
+            for tile in &tiles {
+                let mut copy = tile.clone();
+                copy.position.translation.vector += offset;
+                let center = copy.position.translation.vector;
+                if self.near_level(&center, &center) {
+                    copies.push(copy);
+                }
+            }
+        }
+        tiles.into_iter().chain(copies).collect()
+    }
+
+    pub fn build(&self, world: &mut ::specs::World) {
+        world.maintain();
+        world.delete_all();
+
+        for (position, half_extents) in self.wall_colliders() {
+            ::entity::create_wall(position, half_extents, world);
+        }
+
+        world.add_resource(::resource::Tiles(self.seam_tiles()));
+
+        let tubes = self.tube_pieces();
+        for tube in &tubes {
             ::entity::create_tube(tube, world);
         }
         world.add_resource(::resource::Tubes(tubes));
+        world.add_resource(::resource::Rails::new(&self.paths(), self.unit));
 
-        for _ in 0..self.mine {
-            let pos = maze.random_free();
-            maze.walls.insert(pos);
-            ::entity::create_mine(::util::to_world(&pos, self.unit), world);
+        for entity in &self.entities {
+            let pos = ::util::to_world(&from_array(&entity.position), self.unit);
+            match entity.kind {
+                EntityKind::Mine => ::entity::create_mine(pos, world),
+                EntityKind::Target => ::entity::create_target(pos, world),
+                EntityKind::RocketLauncher => {
+                    let isometry = ::na::Isometry3::new(pos, ::na::zero());
+                    ::entity::create_rocket_launcher(isometry, world);
+                }
+            }
         }
 
-        for _ in 0..self.target{
-            let pos = maze.random_free();
-            maze.walls.insert(pos);
-            ::entity::create_target(::util::to_world(&pos, self.unit), world);
+        let spawns: Vec<_> = self.spawns
+            .iter()
+            .map(|spawn| ::util::to_world(&from_array(spawn), self.unit))
+            .collect();
+        world.add_resource(::resource::Spawns(spawns));
+        world.add_resource(::resource::Navigation::new(self.navigation_maze(), self.unit));
+        world.add_resource(::resource::Wrap {
+            axes: self.wrap,
+            size: from_array(&self.size).map(|s| s as f32 * self.unit),
+        });
+        world.add_resource(::resource::PlayersEntities([None; 3]));
+        world.add_resource(::resource::PlayersLives::new(self.lives));
+        world.add_resource(::resource::CurrentLevel(self.clone()));
+    }
+}
+
+/// Constraint broken by a level
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    UnknownGenerator { name: String },
+    HeightmapSize { width: usize, height: usize, heights: usize },
+    BallTooLarge,
+    NoSpawn,
+    SpawnInWall { spawn: [isize; 3] },
+    UnreachableSpawn { from: [isize; 3], spawn: [isize; 3] },
+    UnreachableTarget { spawn: [isize; 3], target: [isize; 3] },
+    Unplaceable { entity: String, constraint: ::placement::Constraint },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ValidationError::*;
+        match *self {
+            UnknownGenerator { ref name } => write!(
+                f,
+                "unknown maze generator {}, expected one of {}",
+                name,
+                ::generator::NAMES.join(", ")
+            ),
+            HeightmapSize { width, height, heights } => write!(
+                f,
+                "heightmap of {}x{} columns has {} heights",
+                width, height, heights
+            ),
+            BallTooLarge => write!(f, "ball radius {} does not fit in corridors", ::CFG.ball_radius),
+            NoSpawn => write!(f, "level has no spawn"),
+            SpawnInWall { spawn } => write!(f, "spawn {:?} is inside a wall", spawn),
+            UnreachableSpawn { from, spawn } => {
+                write!(f, "spawn {:?} is unreachable from spawn {:?}", spawn, from)
+            }
+            UnreachableTarget { spawn, target } => {
+                write!(f, "target {:?} is unreachable from spawn {:?}", target, spawn)
+            }
+            Unplaceable { ref entity, constraint } => {
+                write!(f, "no cell for {} satisfies the {} rule", entity, constraint)
+            }
         }
+    }
+}
 
-        for _ in 0..self.rocket_launcher {
-            let pos = maze.random_free();
-            maze.walls.insert(pos);
-            let isometry = ::na::Isometry3::new(
-                ::util::to_world(&pos, 1.0),
-                ::na::zero(),
-            );
+impl ::std::error::Error for ValidationError {
+    fn description(&self) -> &str {
+        "invalid level"
+    }
+}
+
+/// No valid level was generated
+#[derive(Clone, Debug, PartialEq)]
+pub struct GenerationError {
+    pub attempts: usize,
+    pub last_error: ValidationError,
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "no valid level after {} attempts, last one failed: {}",
+            self.attempts, self.last_error
+        )
+    }
+}
+
+impl ::std::error::Error for GenerationError {
+    fn description(&self) -> &str {
+        "level generation failed"
+    }
+}
+
+fn clamp(value: isize, min: isize, max: isize) -> isize {
+    value.max(min).min(max)
+}
 
-            ::entity::create_rocket_launcher(isometry, world);
+fn to_array(cell: &::na::Vector3<isize>) -> [isize; 3] {
+    [cell[0], cell[1], cell[2]]
+}
+
+fn from_array(cell: &[isize; 3]) -> ::na::Vector3<isize> {
+    ::na::Vector3::new(cell[0], cell[1], cell[2])
+}
+
+#[cfg(test)]
+fn test_builder() -> LevelBuilder {
+    LevelBuilder {
+        half_size: [4, 4, 4],
+        shape: ::shape::Shape::Cuboid,
+        wrap: [false; 3],
+        x_shift: false,
+        y_shift: false,
+        z_shift: false,
+        percent: 5.0,
+        unit: 1.0,
+        columns: 0,
+        rocket_launcher: 1,
+        mine: 1,
+        target: 1,
+        generator: "kruskal".to_string(),
+        seed: 0,
+        attempts: 20,
+        players: 3,
+        lives: 3,
+        rules: ::placement::PlacementRules::default(),
+    }
+}
+
+#[test]
+fn ron_round_trip() {
+    let level = test_builder().describe().unwrap();
+
+    let string = ::ron::ser::to_string(&level).unwrap();
+    let loaded: LevelDescription = ::ron::de::from_str(&string).unwrap();
+    assert_eq!(level, loaded);
+}
+
+#[test]
+fn builder_is_reproducible() {
+    let builder = LevelBuilder {
+        columns: 2,
+        ..test_builder()
+    };
+    let level = builder.describe().unwrap();
+    assert_eq!(level, builder.describe().unwrap());
+    assert_eq!(level.tiles(), builder.describe().unwrap().tiles());
+}
+
+#[test]
+fn unknown_generator_is_an_error() {
+    let error = LevelBuilder {
+        generator: "unknown".to_string(),
+        ..test_builder()
+    }.describe()
+        .unwrap_err();
+    assert_eq!(error.attempts, 1);
+    assert_eq!(
+        error.last_error,
+        ValidationError::UnknownGenerator {
+            name: "unknown".to_string()
+        }
+    );
+}
+
+#[test]
+fn validation_errors() {
+    let walled = |middle: &str| {
+        let layer = "#####\n#####\n#####\n";
+        let text = format!("-- z 0\n{}-- z 1\n#####\n{}\n#####\n-- z 2\n{}", layer, middle, layer);
+        LevelDescription::from_layers(&text, 1.0, 0).unwrap()
+    };
+
+    let mut level = walled("#S.T#");
+    assert_eq!(level.validate(), Ok(()));
+    // tubes block players as they block homing entities
+    let mut tubed = level.clone();
+    tubed.tubes.push(vec![[2, 1, 1]]);
+    assert_eq!(
+        tubed.validate(),
+        Err(ValidationError::UnreachableTarget {
+            spawn: [1, 1, 1],
+            target: [3, 1, 1],
+        })
+    );
+    level.spawns.push([0, 1, 1]);
+    assert_eq!(level.validate(), Err(ValidationError::SpawnInWall { spawn: [0, 1, 1] }));
+
+    assert_eq!(
+        walled("#S#S#").validate(),
+        Err(ValidationError::UnreachableSpawn {
+            from: [1, 1, 1],
+            spawn: [3, 1, 1],
+        })
+    );
+    assert_eq!(
+        walled("#S#T#").validate(),
+        Err(ValidationError::UnreachableTarget {
+            spawn: [1, 1, 1],
+            target: [3, 1, 1],
+        })
+    );
+}
+
+#[test]
+fn generation_gives_up_after_attempts() {
+    let error = LevelBuilder {
+        unit: 2.0 * ::CFG.ball_radius,
+        attempts: 3,
+        ..test_builder()
+    }.describe()
+        .unwrap_err();
+    assert_eq!(
+        error,
+        GenerationError {
+            attempts: 3,
+            last_error: ValidationError::BallTooLarge,
         }
+    );
+}
+
+#[test]
+fn tune_keeps_parameters_of_returned_level() {
+    let mut builder = test_builder();
+    let level = builder.describe().unwrap();
+    assert_eq!(builder.tune(level.difficulty()).unwrap(), level);
+    assert_eq!(builder.half_size, [4, 4, 4]);
+
+    for &difficulty in &[0.0, 1.0] {
+        let mut builder = test_builder();
+        let level = builder.tune(difficulty).unwrap();
+        assert_eq!(builder.describe().unwrap(), level);
     }
 }
+
+#[test]
+fn mask_is_kept_in_description() {
+    let level = LevelBuilder {
+        shape: ::shape::Shape::Sphere,
+        ..test_builder()
+    }.describe()
+        .unwrap();
+    let mask = level.mask.clone().unwrap();
+    assert!(mask.len() < (level.size[0] * level.size[1] * level.size[2]) as usize);
+    assert!(level.walls.iter().all(|wall| mask.contains(wall)));
+    assert_eq!(level.maze().mask().unwrap().len(), mask.len());
+
+    let error = LevelBuilder {
+        shape: ::shape::Shape::Heightmap {
+            width: 2,
+            height: 2,
+            heights: vec![1.0; 3],
+        },
+        ..test_builder()
+    }.describe()
+        .unwrap_err();
+    assert_eq!(
+        error.last_error,
+        ValidationError::HeightmapSize {
+            width: 2,
+            height: 2,
+            heights: 3,
+        }
+    );
+}
+
+#[test]
+fn wrapped_faces_are_open() {
+    let level = LevelBuilder {
+        shape: ::shape::Shape::Cylinder,
+        wrap: [false, false, true],
+        generator: "prim".to_string(),
+        columns: 2,
+        ..test_builder()
+    }.describe()
+        .unwrap();
+    let maze = level.maze();
+    let size = maze.size();
+
+    // a perfect maze carves the first and last layers, the circle does not close them
+    let open_seams = (0..size[0])
+        .flat_map(|x| (0..size[1]).map(move |y| (x, y)))
+        .filter(|&(x, y)| {
+            !maze.walls.contains(&::na::Vector3::new(x, y, 0))
+                && !maze.walls.contains(&::na::Vector3::new(x, y, size[2] - 1))
+                && maze.is_in_mask(&::na::Vector3::new(x, y, 0))
+        })
+        .count();
+    assert!(open_seams > 0);
+
+    // tubes are not shifted along the wrapped axis
+    for cell in level.tubes.iter().flat_map(|path| path.iter()) {
+        assert!(cell[2] >= 0 && cell[2] < level.size[2]);
+    }
+}
+
+#[test]
+fn seams_copy_walls() {
+    let level = LevelBuilder {
+        wrap: [true, false, false],
+        ..test_builder()
+    }.describe()
+        .unwrap();
+    let size = level.size[0] as f32 * level.unit;
+    let margin = SEAM_MARGIN as f32 * level.unit;
+    let colliders = level.wall_colliders();
+    let inside = |&&(position, half_extents): &&(::na::Vector3<f32>, ::na::Vector3<f32>)| {
+        position[0] - half_extents[0] >= 0.0 && position[0] + half_extents[0] <= size
+    };
+    assert_eq!(colliders.iter().filter(inside).count(), level.maze().wall_cuboids().len());
+
+    // copies only reach the margin beyond the faces
+    let copies = colliders.iter().filter(|c| !inside(c)).collect::<Vec<_>>();
+    assert!(!copies.is_empty());
+    for &&(position, half_extents) in &copies {
+        assert!(position[0] + half_extents[0] > -margin && position[0] - half_extents[0] < size + margin);
+        assert!(position[0] + half_extents[0] <= 0.0 || position[0] - half_extents[0] >= size);
+    }
+
+    let unwrapped = LevelDescription {
+        wrap: [false; 3],
+        ..level.clone()
+    };
+    assert!(unwrapped.wall_colliders().iter().all(|c| inside(&c)));
+}
+
+#[test]
+fn tubes_block_navigation() {
+    let mut level = LevelDescription::from_layers(
+        "\
+-- z 0
+S...T
+",
+        1.0,
+        0,
+    ).unwrap();
+    level.tubes.push(vec![[2, 0, 0]]);
+    let maze = level.navigation_maze();
+    assert!(maze.walls.contains(&::na::Vector3::new(2, 0, 0)));
+    assert!(
+        maze.find_path(::na::Vector3::new(0, 0, 0), ::na::Vector3::new(4, 0, 0))
+            .is_none()
+    );
+    assert!(
+        level.maze()
+            .find_path(::na::Vector3::new(0, 0, 0), ::na::Vector3::new(4, 0, 0))
+            .is_some()
+    );
+}
@@ -32,9 +32,9 @@ extern crate vulkano;
 #[macro_use]
 extern crate vulkano_shader_derive;
 extern crate vulkano_win;
-extern crate wavefront_obj;
 extern crate winit;
 
+mod behaviour;
 mod tube;
 mod tile;
 mod colors;
@@ -52,8 +52,18 @@ mod graphics;
 mod retained_storage;
 mod level;
 mod entity;
+mod export;
+mod generator;
+mod graph;
+mod grid;
+mod metrics;
+mod placement;
+mod shape;
 mod menu;
+mod vox;
 mod world_action;
+#[cfg(test)]
+mod maze_bench;
 
 pub use configuration::CFG;
 
@@ -68,6 +78,21 @@ use specs::{DispatcherBuilder, World};
 use world_action::WorldAction;
 
 fn main() {
+    // headless export: sese export <level.ron> <output.obj|output.glb>
+    // headless import: sese import [--force] [--unit <unit>] <model.vox> <level.ron>
+    let args = ::std::env::args().collect::<Vec<_>>();
+    if args.len() == 4 && args[1] == "export" {
+        if let Err(e) = ::export::export_level(&args[2], &args[3]) {
+            eprintln!("Failed to export level: {}", e);
+            ::std::process::exit(1);
+        }
+        return;
+    }
+    if args.len() >= 2 && args[1] == "import" {
+        import(&args[2..]);
+        return;
+    }
+
     ::std::env::set_var("WINIT_UNIX_BACKEND", "x11");
     let mut save = ::resource::Save::new();
 
@@ -103,24 +128,32 @@ fn main() {
     world.register::<::component::Contactor>();
     world.register::<::component::Target>();
     world.register::<::component::PlayerKiller>();
+    world.register::<::component::Health>();
     world.register::<::component::RocketLauncher>();
-    world.register::<::component::RocketControl>();
-    world.register::<::component::MineControl>();
+    world.register::<::component::Behaviour>();
+    world.register::<::component::Visibility>();
     world.register::<::component::ClosestPlayer>();
+    world.register::<::component::Rider>();
     world.add_resource(::resource::UpdateTime(0.0));
     world.add_resource(::resource::PhysicWorld::new());
     world.add_resource(::resource::PlayersEntities([None; 3]));
     world.add_resource(::resource::PlayersControllers([None, None, None]));
+    world.add_resource(::resource::PlayersLives::new(::CFG.lives));
+    world.add_resource(::resource::PlayersTolerances([::resource::Tolerance::default(); 3]));
     world.add_resource(::resource::Mode::Mode1Player);
     world.add_resource(::resource::Text::default());
     world.add_resource(::resource::Font::new());
     world.maintain();
 
     let mut update_dispatcher = DispatcherBuilder::new()
-        .with(::system::physic::PhysicSystem, "physic", &[])
+        .with(::system::behaviour::BehaviourSystem, "behaviour", &[])
+        .with(::system::physic::PhysicSystem, "physic", &["behaviour"])
+        .with(::system::wrap::WrapSystem, "wrap", &["physic"])
+        .with(::system::rail::RailSystem, "rail", &["wrap"])
         .with(::system::target::TargetSystem, "target", &["physic"])
-        .with(::system::player_killer::PlayerKillerSystem, "player killer", &[])
-        .with(::system::rocket_launcher::RocketLauncherSystem, "rocket launcher", &[])
+        .with(::system::player_killer::PlayerKillerSystem, "player killer", &["physic"])
+        .with(::system::visibility::VisibilitySystem, "visibility", &["physic"])
+        .with(::system::rocket_launcher::RocketLauncherSystem, "rocket launcher", &["visibility"])
         .with(::system::closest_player::ClosestPlayerSystem, "closest player", &[])
         .with(::system::player_creator::PlayerCreatorSystem, "player creator", &[])
         .with_barrier() // Draw barrier
@@ -134,7 +167,9 @@ fn main() {
     let mut game_state = Box::new(game_state::GlobalMenu::new(&world)) as Box<GameState>;
 
     ::level::LevelBuilder {
-        half_size: 9,
+        half_size: [9, 9, 9],
+        shape: ::shape::Shape::Cuboid,
+        wrap: [false; 3],
         x_shift: false,
         y_shift: false,
         z_shift: false,
@@ -144,7 +179,14 @@ fn main() {
         rocket_launcher: 1,
         mine: 1,
         target: 1,
-    }.build(&mut world);
+        generator: "kruskal".to_string(),
+        seed: ::rand::random(),
+        attempts: 20,
+        players: 3,
+        lives: ::CFG.lives,
+        rules: ::placement::PlacementRules::default(),
+    }.build(&mut world)
+        .ok_or_show(|e| format!("Failed to generate level: {}", e));
 
     'main_loop: loop {
         // Parse events
@@ -223,3 +265,48 @@ fn main() {
         fps_counter.tick();
     }
 }
+
+/// Import a vox model as a level, unplayable levels are only saved with `--force`
+fn import(args: &[String]) {
+    let usage = "Usage: sese import [--force] [--unit <unit>] <model.vox> <level.ron>";
+    let mut force = false;
+    let mut unit = 1.0;
+    let mut paths = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => force = true,
+            "--unit" => {
+                unit = match args.next().and_then(|unit| unit.parse::<f32>().ok()) {
+                    Some(unit) if unit > 0.0 => unit,
+                    _ => {
+                        eprintln!("--unit expects a positive number\n{}", usage);
+                        ::std::process::exit(1);
+                    }
+                }
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.len() != 2 {
+        eprintln!("{}", usage);
+        ::std::process::exit(1);
+    }
+
+    let level = ::level::LevelDescription::from_vox(paths[0], &::CFG.vox_markers, unit, ::rand::random())
+        .unwrap_or_else(|e| {
+            eprintln!("Failed to import level: {}", e);
+            ::std::process::exit(1);
+        });
+    if let Err(e) = level.validate() {
+        eprintln!("Imported level is not playable: {}", e);
+        if !force {
+            eprintln!("Level not saved, use --force to save it anyway");
+            ::std::process::exit(1);
+        }
+    }
+    if let Err(e) = level.save_to_file(paths[1]) {
+        eprintln!("Failed to save level: {}", e);
+        ::std::process::exit(1);
+    }
+}
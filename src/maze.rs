@@ -1,25 +1,12 @@
 use rand::distributions::{Distribution, Range};
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashSet;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::ops::Mul;
 
-trait Pop {
-    type Item;
-    fn pop(&mut self) -> Option<Self::Item>;
-}
-
-impl<T: Eq + Hash + Clone> Pop for HashSet<T> {
-    type Item = T;
-    fn pop(&mut self) -> Option<Self::Item> {
-        self.iter()
-            .next()
-            .map(|cell| cell.clone())
-            .map(|cell| self.take(&cell).unwrap())
-    }
-}
-
 #[derive(Clone)]
 struct Opening<D>
 where
@@ -39,24 +26,92 @@ where
     D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
         + ::generic_array::ArrayLength<isize>,
 {
-    pub walls: HashSet<::na::VectorN<isize, D>>,
+    /// Walls are cells of the box, `insert` panics outside of it
+    pub walls: ::grid::Grid<D>,
     size: ::na::VectorN<isize, D>,
     openings: Vec<Opening<D>>,
     pub neighbours: Vec<::na::VectorN<isize, D>>,
+    /// Cells belonging to the maze, all cells of the box if none
+    mask: Option<HashSet<::na::VectorN<isize, D>>>,
+    /// Axes whose opposite faces are adjacent
+    wrap: Vec<bool>,
 }
 
 impl Maze<::na::U3> {
-    /// take a random wall, set the larger not colored cuboid containing it to one color
+    /// Give a color below `colors` to every wall, the same to a connected wall region
     ///
-    /// continue while some wall are not colored
-    pub fn build_colors(&self) -> HashMap<::na::Vector3<isize>, usize> {
-        let mut wall_random_list = self.walls.iter().cloned().collect::<Vec<_>>();
-        thread_rng().shuffle(&mut wall_random_list);
+    /// Regions around a common free cell are adjacent, they get different colors when
+    /// possible: regions are colored by decreasing saturation (DSatur)
+    pub fn build_colors<R: Rng>(&self, colors: usize, rng: &mut R) -> HashMap<::na::Vector3<isize>, usize> {
+        assert!(colors > 0);
+        let regions = self.wall_regions(rng);
+        let region_count = regions.values().max().map_or(0, |&r| r + 1);
+
+        let mut adjacents = vec![HashSet::new(); region_count];
+        let mut free_cells = self.walls
+            .iter()
+            .flat_map(|wall| self.neighbours.iter().map(move |n| n + wall))
+            .filter(|cell| !self.walls.contains(cell))
+            .collect::<Vec<_>>();
+        free_cells.sort_by(|a, b| a.iter().cmp(b.iter()));
+        free_cells.dedup();
+        for cell in free_cells {
+            let around = self.neighbours
+                .iter()
+                .filter_map(|n| regions.get(&(n + cell)))
+                .cloned()
+                .collect::<HashSet<_>>();
+            for &region in &around {
+                adjacents[region].extend(around.iter().filter(|&&other| other != region));
+            }
+        }
+
+        let mut region_colors: Vec<Option<usize>> = vec![None; region_count];
+        for _ in 0..region_count {
+            let region = {
+                let saturation = |region: usize| {
+                    adjacents[region]
+                        .iter()
+                        .filter_map(|&other| region_colors[other])
+                        .collect::<HashSet<_>>()
+                        .len()
+                };
+                (0..region_count)
+                    .filter(|&region| region_colors[region].is_none())
+                    .max_by_key(|&region| (saturation(region), adjacents[region].len(), Reverse(region)))
+                    .unwrap()
+            };
 
-        let mut color = 0;
-        let mut colored = HashMap::new();
+            // the color the fewest adjacent regions have
+            let mut uses = vec![0; colors];
+            for &other in &adjacents[region] {
+                if let Some(color) = region_colors[other] {
+                    uses[color] += 1;
+                }
+            }
+            let color = (0..colors).min_by_key(|&color| uses[color]).unwrap();
+            region_colors[region] = Some(color);
+        }
+
+        regions
+            .into_iter()
+            .map(|(wall, region)| (wall, region_colors[region].unwrap()))
+            .collect()
+    }
+
+    /// take a random wall, set the larger not numbered connected walls containing it to one
+    /// region
+    ///
+    /// continue while some wall are not numbered
+    fn wall_regions<R: Rng>(&self, rng: &mut R) -> HashMap<::na::Vector3<isize>, usize> {
+        // walls are in grid order so the shuffle only depends on the rng
+        let mut wall_random_list = self.walls.iter().collect::<Vec<_>>();
+        rng.shuffle(&mut wall_random_list);
+
+        let mut region = 0;
+        let mut regions = HashMap::new();
         for wall in wall_random_list {
-            if colored.contains_key(&wall) {
+            if regions.contains_key(&wall) {
                 continue;
             }
 
@@ -66,16 +121,55 @@ impl Maze<::na::U3> {
                     .iter()
                     .map(|n| n + wall)
                     .filter(|n| self.walls.contains(n))
-                    .filter(|n| !colored.contains_key(n))
+                    .filter(|n| !regions.contains_key(n))
                     .for_each(|n| expand.push(n));
 
-                colored.insert(wall, color);
+                regions.insert(wall, region);
             }
 
-            color += 1;
+            region += 1;
         }
 
-        colored
+        regions
+    }
+
+    /// Cover the walls with few cuboids, returned as first cell and size
+    ///
+    /// Greedy: from the first wall not covered in grid order grow along z, then y, then x
+    pub fn wall_cuboids(&self) -> Vec<(::na::Vector3<isize>, ::na::Vector3<isize>)> {
+        let walls = self.walls.iter().collect::<Vec<_>>();
+        let mut uncovered = self.walls.clone();
+
+        let mut cuboids = vec![];
+        for start in &walls {
+            if !uncovered.contains(start) {
+                continue;
+            }
+
+            let mut size = ::na::Vector3::new(1, 1, 1);
+            for axis in (0..3).rev() {
+                loop {
+                    // the next layer of the cuboid along the axis
+                    let mut layer_size = size;
+                    layer_size[axis] = 1;
+                    let mut layer_start = *start;
+                    layer_start[axis] += size[axis];
+                    let layer_full = Self::iterate_area(&layer_size)
+                        .iter()
+                        .all(|cell| uncovered.contains(&(cell + layer_start)));
+                    if !layer_full {
+                        break;
+                    }
+                    size[axis] += 1;
+                }
+            }
+
+            for cell in Self::iterate_area(&size) {
+                uncovered.remove(&(cell + start));
+            }
+            cuboids.push((*start, size));
+        }
+        cuboids
     }
 }
 
@@ -87,180 +181,407 @@ where
 {
     pub fn new_empty() -> Self {
         Maze {
-            walls: HashSet::new(),
+            walls: ::grid::Grid::new(::na::zero()),
             size: ::na::zero(),
             openings: Self::openings(),
             neighbours: Self::neighbours(),
+            mask: None,
+            wrap: vec![false; D::dim()],
         }
     }
 
     pub fn new_rectangle(size: ::na::VectorN<isize, D>) -> Self {
         Maze {
-            walls: HashSet::new(),
+            walls: ::grid::Grid::new(size.clone()),
             size: size,
             openings: Self::openings(),
             neighbours: Self::neighbours(),
+            mask: None,
+            wrap: vec![false; D::dim()],
         }
     }
 
     /// Generate partial reverse randomized_kruskal
     /// `https://en.wikipedia.org/wiki/Maze_generation_algorithm#Randomized_Kruskal`
-    pub fn new_kruskal(
+    pub fn new_kruskal<R: Rng>(
         size: ::na::VectorN<isize, D>,
         percent: f64,
         bug: ::na::VectorN<isize, D>,
+        rng: &mut R,
     ) -> Self {
-        struct GridCell {
-            wall: bool,
-            group: usize,
-        }
-
         for size in size.iter() {
             assert_eq!(size.wrapping_rem(2), 1);
         }
 
-        let mut grid = HashMap::new();
-
-        for (i, cell) in Self::iterate_area(&size).iter().enumerate() {
-            grid.insert(
-                cell.clone(),
-                GridCell {
-                    wall: false,
-                    group: i,
-                },
-            );
-        }
+        let area = ::grid::Grid::new(size.clone());
+        let mut is_wall = vec![false; area.cell_count()];
+        let mut groups = ::grid::UnionFind::new(area.cell_count());
+
+        // For each axis a wall of 3^(n-1) cells orthogonal to it and centered on 0
+        let axis_walls = (0..D::dim())
+            .map(|axis| {
+                let three = ::na::VectorN::<isize, D>::from_iterator(
+                    (0..D::dim()).map(|i| if i == axis { 1 } else { 3 }),
+                );
+                let center = ::na::VectorN::<isize, D>::from_iterator(
+                    (0..D::dim()).map(|i| if i == axis { 0 } else { 1 }),
+                );
+                Self::iterate_area(&three)
+                    .into_iter()
+                    .map(|v| v - center.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
 
+        let half = size.map(|s| s / 2);
         let mut walls: Vec<Vec<::na::VectorN<isize, D>>> = Vec::new();
-        let mut x_wall = vec![]; // 1x3x3 wall centered on 0
-        let mut y_wall = vec![]; // 3x1x3 wall centered on 0
-        let mut z_wall = vec![]; // 3x3x1 wall centered on 0
-
-        match D::dim() {
-            2 => {
-                for i in -1..2 {
-                    x_wall.push(Self::new_vec2(0, i));
-                    y_wall.push(Self::new_vec2(i, 0));
+        for grid_cell in Self::iterate_area(&half) {
+            let grid_cell = grid_cell.map(|c| c + 1);
+            for (axis, axis_wall) in axis_walls.iter().enumerate() {
+                let other_end = (0..D::dim()).any(|i| i != axis && grid_cell[i] == half[i]);
+                if other_end {
+                    continue;
                 }
+                let mut position = grid_cell.map(|c| c * 2);
+                position[axis] += bug[axis] - 1;
+                walls.push(axis_wall.iter().map(|c| c + position.clone()).collect());
+            }
+        }
 
-                for x in 1..size[0] / 2 + 1 {
-                    for y in 1..size[1] / 2 + 1 {
-                        if y != size[1] / 2 {
-                            walls.push(
-                                x_wall
-                                    .iter()
-                                    .map(|c| c + Self::new_vec2(x * 2 - 1 + bug[0], y * 2))
-                                    .collect(),
-                            );
-                        }
-                        if x != size[0] / 2 {
-                            walls.push(
-                                y_wall
-                                    .iter()
-                                    .map(|c| c + Self::new_vec2(x * 2, y * 2 - 1 + bug[1]))
-                                    .collect(),
-                            );
-                        }
-                    }
+        let stop = ((walls.len() as f64) * (1. - percent / 100.)) as usize;
+
+        while walls.len() > stop {
+            let i = ::rand::distributions::Range::new(0, walls.len()).sample(rng);
+            let wall = walls.swap_remove(i);
+
+            let indices = wall.iter()
+                .map(|cell| area.index(cell).unwrap() as usize)
+                .collect::<Vec<_>>();
+            let mut roots = indices.iter().map(|&i| groups.find(i)).collect::<Vec<_>>();
+            roots.sort();
+            roots.dedup();
+
+            if roots.len() > 2 {
+                for &i in &indices {
+                    is_wall[i] = true;
                 }
-            }
-            3 => {
-                for i in -1..2 {
-                    for j in -1..2 {
-                        x_wall.push(Self::new_vec3(0, i, j));
-                        y_wall.push(Self::new_vec3(i, 0, j));
-                        z_wall.push(Self::new_vec3(i, j, 0));
-                    }
+                for &root in &roots[1..] {
+                    groups.union(roots[0], root);
                 }
+            }
+        }
 
-                for x in 1..size[0] / 2 + 1 {
-                    for y in 1..size[1] / 2 + 1 {
-                        for z in 1..size[2] / 2 + 1 {
-                            let x_end = x == size[0] / 2;
-                            let y_end = y == size[1] / 2;
-                            let z_end = z == size[2] / 2;
-                            if !y_end && !z_end {
-                                walls.push(
-                                    x_wall
-                                        .iter()
-                                        .map(|c| {
-                                            c + Self::new_vec3(x * 2 - 1 + bug[0], y * 2, z * 2)
-                                        })
-                                        .collect(),
-                                );
-                            }
-                            if !x_end && !z_end {
-                                walls.push(
-                                    y_wall
-                                        .iter()
-                                        .map(|c| {
-                                            c + Self::new_vec3(x * 2, y * 2 - 1 + bug[1], z * 2)
-                                        })
-                                        .collect(),
-                                );
-                            }
-                            if !x_end && !y_end {
-                                walls.push(
-                                    z_wall
-                                        .iter()
-                                        .map(|c| {
-                                            c + Self::new_vec3(x * 2, y * 2, z * 2 - 1 + bug[2])
-                                        })
-                                        .collect(),
-                                );
-                            }
-                        }
-                    }
+        let walls = ::grid::Grid::from_cells(
+            size.clone(),
+            is_wall
+                .iter()
+                .enumerate()
+                .filter(|&(_, &wall)| wall)
+                .map(|(i, _)| area.cell(i as u32)),
+        );
+
+        Maze {
+            size,
+            walls,
+            neighbours: Self::neighbours(),
+            openings: Self::openings(),
+            mask: None,
+            wrap: vec![false; D::dim()],
+        }
+    }
+
+    /// Generate a perfect maze with randomized Prim's algorithm
+    /// `https://en.wikipedia.org/wiki/Maze_generation_algorithm#Randomized_Prim's_algorithm`
+    pub fn new_prim<R: Rng>(
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        rng: &mut R,
+    ) -> Self {
+        let mut maze = Self::new_filled(size, mask);
+        let start = match maze.random_grid_cell(rng) {
+            Some(start) => start,
+            None => return maze,
+        };
+        maze.walls.remove(&start);
+
+        let mut frontier = maze.grid_neighbours(&start)
+            .into_iter()
+            .map(|neighbour| (start.clone(), neighbour))
+            .collect::<Vec<_>>();
+
+        while !frontier.is_empty() {
+            let i = Range::new(0, frontier.len()).sample(rng);
+            let (from, to) = frontier.swap_remove(i);
+            if !maze.walls.contains(&to) {
+                continue;
+            }
+            maze.carve(&from, &to);
+            for neighbour in maze.grid_neighbours(&to) {
+                if maze.walls.contains(&neighbour) {
+                    frontier.push((to.clone(), neighbour));
                 }
             }
-            _ => unimplemented!(),
         }
 
-        let mut rng = ::rand::thread_rng();
+        maze
+    }
 
-        let stop = ((walls.len() as f64) * (1. - percent / 100.)) as usize;
+    /// Generate a perfect maze with the growing tree algorithm
+    ///
+    /// the active cell is the newest one with probability `newest` and a random one otherwise
+    /// `http://weblog.jamisbuck.org/2011/1/27/maze-generation-growing-tree-algorithm`
+    pub fn new_growing_tree<R: Rng>(
+        size: ::na::VectorN<isize, D>,
+        newest: f64,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        rng: &mut R,
+    ) -> Self {
+        assert!(newest >= 0.0 && newest <= 1.0);
+
+        let mut maze = Self::new_filled(size, mask);
+        let start = match maze.random_grid_cell(rng) {
+            Some(start) => start,
+            None => return maze,
+        };
+        maze.walls.remove(&start);
+
+        let mut actives = vec![start];
+        while !actives.is_empty() {
+            let i = if rng.gen::<f64>() < newest {
+                actives.len() - 1
+            } else {
+                Range::new(0, actives.len()).sample(rng)
+            };
 
-        while walls.len() > stop {
-            let i = ::rand::distributions::Range::new(0, walls.len()).sample(&mut rng);
-            let wall = walls.swap_remove(i);
+            let mut candidates = maze.grid_neighbours(&actives[i]);
+            candidates.retain(|neighbour| maze.walls.contains(neighbour));
 
-            let mut groups = HashSet::new();
-            for cell in &wall {
-                groups.insert(grid[cell].group);
+            if candidates.is_empty() {
+                actives.remove(i);
+            } else {
+                let next = candidates.swap_remove(Range::new(0, candidates.len()).sample(rng));
+                let cell = actives[i].clone();
+                maze.carve(&cell, &next);
+                actives.push(next);
             }
-            let one_group = grid[&wall[0]].group; // a random group in the set
+        }
+
+        maze
+    }
+
+    /// Generate a perfect maze with the recursive backtracker
+    /// `https://en.wikipedia.org/wiki/Maze_generation_algorithm#Recursive_backtracker`
+    pub fn new_recursive_backtracker<R: Rng>(
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        rng: &mut R,
+    ) -> Self {
+        // growing tree that always continue from the newest cell is a backtracker
+        Self::new_growing_tree(size, 1.0, mask, rng)
+    }
+
+    /// Generate a perfect maze with Eller's algorithm
+    ///
+    /// rows are the slices along the last axis
+    /// `http://weblog.jamisbuck.org/2010/12/29/maze-generation-eller-s-algorithm`
+    pub fn new_eller<R: Rng>(
+        size: ::na::VectorN<isize, D>,
+        mask: Option<HashSet<::na::VectorN<isize, D>>>,
+        rng: &mut R,
+    ) -> Self {
+        let mut maze = Self::new_filled(size, mask);
+        let last = D::dim() - 1;
+        let rows = (0..maze.size[last])
+            .filter(|c| c % 2 == 1)
+            .collect::<Vec<_>>();
 
-            if groups.len() > 2 {
-                for cell in &wall {
-                    grid.get_mut(cell).unwrap().wall = true
+        let mut next_set = 0;
+        // set of cells of the current row that have been carved from the previous row
+        let mut sets: HashMap<::na::VectorN<isize, D>, usize> = HashMap::new();
+
+        for (i, &row) in rows.iter().enumerate() {
+            let last_row = i == rows.len() - 1;
+            let cells = maze.grid_cells()
+                .into_iter()
+                .filter(|cell| cell[last] == row)
+                .collect::<Vec<_>>();
+
+            for cell in &cells {
+                maze.walls.remove(cell);
+                if !sets.contains_key(cell) {
+                    sets.insert(cell.clone(), next_set);
+                    next_set += 1;
                 }
-                for cell in grid.values_mut() {
-                    if groups.contains(&cell.group) {
-                        cell.group = one_group;
+            }
+
+            // Join cells of the row
+            for cell in &cells {
+                for neighbour in maze.grid_neighbours(cell) {
+                    // each pair of cells only once
+                    let forward = neighbour.iter().cmp(cell.iter()) == ::std::cmp::Ordering::Greater;
+                    if neighbour[last] != row || !forward {
+                        continue;
+                    }
+                    let (set, neighbour_set) = (sets[cell], sets[&neighbour]);
+                    if set != neighbour_set && (last_row || rng.gen::<bool>()) {
+                        maze.carve(cell, &neighbour);
+                        for s in sets.values_mut() {
+                            if *s == neighbour_set {
+                                *s = set;
+                            }
+                        }
                     }
                 }
             }
-        }
 
-        let mut walls = HashSet::new();
-        for (key, value) in grid {
-            if value.wall {
-                walls.insert(key);
+            if last_row {
+                break;
+            }
+
+            // Carve at least one cell of each set to the next row
+            let mut next_sets = HashMap::new();
+            let mut set_cells: HashMap<usize, Vec<::na::VectorN<isize, D>>> = HashMap::new();
+            for cell in &cells {
+                set_cells.entry(sets[cell]).or_insert_with(Vec::new).push(cell.clone());
             }
+            let mut set_ids = set_cells.keys().cloned().collect::<Vec<_>>();
+            set_ids.sort();
+            for set in set_ids {
+                let below = |cell: &::na::VectorN<isize, D>| {
+                    let mut below = cell.clone();
+                    below[last] += 2;
+                    below
+                };
+                let mut set_cells = set_cells.remove(&set).unwrap();
+                // sets whose cells are all above the border of the mask end here
+                set_cells.retain(|cell| maze.is_in_mask(&below(cell)));
+                rng.shuffle(&mut set_cells);
+                for (j, cell) in set_cells.iter().enumerate() {
+                    if j == 0 || rng.gen::<bool>() {
+                        let below = below(cell);
+                        maze.carve(cell, &below);
+                        next_sets.insert(below, set);
+                    }
+                }
+            }
+            sets = next_sets;
         }
 
-        Maze {
+        maze
+    }
+
+    /// Maze full of walls, perfect maze generators carve their cells in it
+    ///
+    /// Only cells of the mask are filled and carved
+    fn new_filled(size: ::na::VectorN<isize, D>, mask: Option<HashSet<::na::VectorN<isize, D>>>) -> Self {
+        for size in size.iter() {
+            assert_eq!(size.wrapping_rem(2), 1);
+            assert!(*size >= 3);
+        }
+
+        let mut maze = Maze {
+            walls: ::grid::Grid::from_cells(size.clone(), Self::iterate_area(&size)),
             size,
-            walls,
             neighbours: Self::neighbours(),
             openings: Self::openings(),
+            mask: None,
+            wrap: vec![false; D::dim()],
+        };
+        if let Some(mask) = mask {
+            maze.set_mask(mask);
         }
+        maze
+    }
+
+    /// Cells of perfect mazes: cells with all coordinates odd
+    fn grid_cells(&self) -> Vec<::na::VectorN<isize, D>> {
+        self.iterate_maze()
+            .into_iter()
+            .filter(|cell| cell.iter().all(|c| c % 2 == 1))
+            .collect()
+    }
+
+    /// None if no grid cell is in the mask
+    fn random_grid_cell<R: Rng>(&self, rng: &mut R) -> Option<::na::VectorN<isize, D>> {
+        let mut cells = self.grid_cells();
+        if cells.is_empty() {
+            return None;
+        }
+        let i = Range::new(0, cells.len()).sample(rng);
+        Some(cells.swap_remove(i))
+    }
+
+    /// Grid cells of the mask separated from the cell by one wall
+    fn grid_neighbours(&self, cell: &::na::VectorN<isize, D>) -> Vec<::na::VectorN<isize, D>> {
+        self.neighbours
+            .iter()
+            .map(|n| n.clone() * 2 + cell)
+            .filter(|n| !self.is_on_border(n) && self.is_in_mask(n))
+            .collect()
+    }
+
+    /// Remove the walls of both grid cells and the wall between them
+    fn carve(&mut self, from: &::na::VectorN<isize, D>, to: &::na::VectorN<isize, D>) {
+        let between = (from + to) / 2;
+        self.walls.remove(from);
+        self.walls.remove(&between);
+        self.walls.remove(to);
     }
 
     pub fn size(&self) -> ::na::VectorN<isize, D> {
         self.size.clone()
     }
 
+    /// Restrict the maze to the cells of the mask, walls outside of it are removed
+    ///
+    /// Zones, `circle` and `free_cells` only consider cells of the mask
+    pub fn set_mask(&mut self, mask: HashSet<::na::VectorN<isize, D>>) {
+        self.walls.retain(|wall| mask.contains(wall));
+        self.mask = Some(mask);
+    }
+
+    pub fn mask(&self) -> Option<&HashSet<::na::VectorN<isize, D>>> {
+        self.mask.as_ref()
+    }
+
+    pub fn is_in_mask(&self, cell: &::na::VectorN<isize, D>) -> bool {
+        self.mask.as_ref().map_or(true, |mask| mask.contains(cell))
+    }
+
+    /// Make opposite faces of the axis adjacent
+    pub fn set_wrap(&mut self, axis: usize, wrap: bool) {
+        self.wrap[axis] = wrap;
+    }
+
+    pub fn is_wrapped(&self, axis: usize) -> bool {
+        self.wrap[axis]
+    }
+
+    /// Cell at the offset of the cell, across the faces of wrapped axes
+    pub fn neighbour(
+        &self,
+        cell: &::na::VectorN<isize, D>,
+        offset: &::na::VectorN<isize, D>,
+    ) -> ::na::VectorN<isize, D> {
+        let mut res = cell + offset;
+        for i in 0..D::dim() {
+            if self.wrap[i] && self.size[i] > 0 {
+                res[i] = ((res[i] % self.size[i]) + self.size[i]) % self.size[i];
+            }
+        }
+        res
+    }
+
+    /// Distance along the axis, the shortest way around for wrapped axes
+    fn axis_distance(&self, a: &::na::VectorN<isize, D>, b: &::na::VectorN<isize, D>, axis: usize) -> isize {
+        let distance = (a[axis] - b[axis]).abs();
+        if self.wrap[axis] {
+            distance.min(self.size[axis] - distance)
+        } else {
+            distance
+        }
+    }
+
     pub fn is_cuboid(&self) -> bool {
         for &s in self.size.iter() {
             if s != self.size[0] {
@@ -273,45 +594,70 @@ where
     pub fn check(&self) {
         let zero = ::na::VectorN::<isize, D>::from_iterator((0..1).cycle());
         for wall in self.walls.iter() {
-            assert!(wall < &self.size);
-            assert!(wall >= &zero);
+            assert!(wall < self.size);
+            assert!(wall >= zero);
         }
     }
 
     /// Remove the circle of the maze
     pub fn reduce(&mut self, size: isize) {
         assert!(size > 0);
-        for &s in self.size.iter() {
-            assert!(s >= size * 2);
+        self.reduce_by(size * ::na::VectorN::<isize, D>::from_iterator((1..2).cycle()));
+    }
+
+    /// Remove a circle of the given size on each axis, undo `extend` with its offset
+    pub fn reduce_by(&mut self, dl: ::na::VectorN<isize, D>) {
+        for (&s, &size) in self.size.iter().zip(dl.iter()) {
+            assert!(size >= 0 && s >= size * 2);
         }
-        let dl = size * ::na::VectorN::<isize, D>::from_iterator((1..2).cycle());
-        let mut new_walls = HashSet::new();
+        let end = self.size.clone() - dl.clone();
+        let mut new_walls = ::grid::Grid::new(end.clone() - dl.clone());
         for wall in self.walls.iter() {
-            if wall >= &dl && wall < &(self.size.clone() - dl.clone()) {
-                new_walls.insert(wall - dl.clone());
+            if wall >= dl && wall < end {
+                new_walls.insert(&(wall - dl.clone()));
             }
         }
         self.walls = new_walls;
+        if let Some(ref mut mask) = self.mask {
+            *mask = mask.iter()
+                .filter(|cell| *cell >= &dl && *cell < &end)
+                .map(|cell| cell - dl.clone())
+                .collect();
+        }
         self.size -= dl * 2;
     }
 
-    /// Extend the maze with empty cell
-    pub fn extend(&mut self, size: isize) {
-        let dl = size * ::na::VectorN::<isize, D>::from_iterator((1..2).cycle());
-        let mut new_walls = HashSet::new();
+    /// Extend the maze with empty cell, wrapped axes are not extended
+    ///
+    /// Return the offset added to the cells
+    pub fn extend(&mut self, size: isize) -> ::na::VectorN<isize, D> {
+        let dl = ::na::VectorN::<isize, D>::from_iterator(
+            self.wrap.iter().map(|&wrap| if wrap { 0 } else { size }),
+        );
+        let mut new_walls = ::grid::Grid::new(self.size.clone() + dl.clone() * 2);
         for wall in self.walls.iter() {
-            new_walls.insert(wall + dl.clone());
+            new_walls.insert(&(wall + dl.clone()));
         }
         self.walls = new_walls;
-        self.size += dl * 2;
+        if let Some(ref mut mask) = self.mask {
+            *mask = mask.iter().map(|cell| cell + dl.clone()).collect();
+        }
+        self.size += dl.clone() * 2;
+        dl
     }
 
-    /// Create a wall that circle the maze
+    /// Create a wall that circle the maze, following the mask if any
     pub fn circle(&mut self) {
         for cell in self.iterate_maze() {
-            for i in 0..D::dim() {
+            let outside_neighbour = self.neighbours
+                .iter()
+                .any(|n| !self.is_in_mask(&self.neighbour(&cell, n)));
+            if outside_neighbour {
+                self.walls.insert(&cell);
+            }
+            for i in (0..D::dim()).filter(|&i| !self.wrap[i]) {
                 if cell[i] == 0 || cell[i] == self.size[i] - 1 {
-                    self.walls.insert(cell.clone());
+                    self.walls.insert(&cell);
                 }
             }
         }
@@ -320,16 +666,17 @@ where
     /// Filter allowed entry.
     /// Return cell and its opening.
     /// The vector returned may contains less than nbr cell if it can't dig further.
-    pub fn dig_cells<F>(
+    pub fn dig_cells<F, R>(
         &mut self,
         nbr: usize,
         filter: F,
+        rng: &mut R,
     ) -> Vec<(::na::VectorN<isize, D>, ::na::VectorN<isize, D>)>
     where
         F: Fn(&::na::VectorN<isize, D>) -> bool,
+        R: Rng,
     {
         let mut res = vec![];
-        let mut rng = ::rand::thread_rng();
         let mut candidates = self.iterate_maze();
         candidates.retain(|cell| filter(cell));
 
@@ -337,19 +684,19 @@ where
             candidates.retain(|cell| {
                 self.neighbours
                     .iter()
-                    .map(|n| n + cell)
+                    .map(|n| self.neighbour(cell, n))
                     .filter(|n| !self.walls.contains(n))
                     .count() == 1 && !self.is_on_border(cell)
             });
             if candidates.is_empty() {
                 return res;
             }
-            let choosen = Range::new(0, candidates.len()).sample(&mut rng);
+            let choosen = Range::new(0, candidates.len()).sample(rng);
             let cell = candidates.swap_remove(choosen);
             self.walls.remove(&cell);
             let opening = self.neighbours
                 .iter()
-                .map(|n| n + cell.clone())
+                .map(|n| self.neighbour(&cell, n))
                 .filter(|n| !self.walls.contains(n))
                 .next()
                 .unwrap();
@@ -382,7 +729,7 @@ where
         let mut changes = false;
         zones.iter().flat_map(|zone| zone.iter()).for_each(|pos| {
             changes = true;
-            self.walls.insert(pos.clone());
+            self.walls.insert(pos);
         });
         changes
     }
@@ -392,7 +739,7 @@ where
         let rooms = self.compute_dead_room_zones();
         for pos in rooms.iter().flat_map(|z| z) {
             changes = true;
-            self.walls.insert(pos.clone());
+            self.walls.insert(pos);
         }
         changes
     }
@@ -403,14 +750,10 @@ where
             let mut corridors = self.compute_corridor_zones();
             corridors.retain(|corridor| {
                 corridor.iter().any(|cell| {
-                    let neighbours_wall =
-                        self.neighbours.iter().map(|n| n + cell).fold(0, |acc, n| {
-                            if self.walls.contains(&n) {
-                                acc + 1
-                            } else {
-                                acc
-                            }
-                        });
+                    let neighbours_wall = self.neighbours
+                        .iter()
+                        .filter(|n| self.walls.contains(&self.neighbour(cell, n)))
+                        .count();
                     neighbours_wall >= self.neighbours.len() - 1
                 })
             });
@@ -419,7 +762,7 @@ where
             }
             for pos in corridors.iter().flat_map(|z| z) {
                 changes = true;
-                self.walls.insert(pos.clone());
+                self.walls.insert(pos);
             }
         }
         changes
@@ -428,17 +771,30 @@ where
     pub fn is_neighbouring_corridor(&self, cell: &::na::VectorN<isize, D>) -> bool {
         self.neighbours
             .iter()
-            .map(|n| n + cell)
+            .map(|n| self.neighbour(cell, n))
             .any(|n| self.is_corridor(&n))
     }
 
     pub fn is_neighbouring_wall(&self, cell: &::na::VectorN<isize, D>) -> bool {
         self.neighbours
             .iter()
-            .map(|n| n + cell)
+            .map(|n| self.neighbour(cell, n))
             .any(|n| self.walls.contains(&n))
     }
 
+    /// Number of openings free of walls around a free cell
+    fn free_openings(&self, cell: &::na::VectorN<isize, D>) -> usize {
+        self.openings
+            .iter()
+            .filter(|opening| {
+                opening
+                    .requires
+                    .iter()
+                    .all(|o| !self.walls.contains(&self.neighbour(cell, o)))
+            })
+            .count()
+    }
+
     pub fn is_corridor(&self, cell: &::na::VectorN<isize, D>) -> bool {
         !self.walls.contains(cell)
             && self.openings
@@ -447,37 +803,45 @@ where
                     opening
                         .requires
                         .iter()
-                        .all(|o| !self.walls.contains(&(cell.clone() + o)))
+                        .all(|o| !self.walls.contains(&self.neighbour(cell, o)))
                 })
                 .count() <= 2
     }
 
+    pub fn is_inside(&self, v: &::na::VectorN<isize, D>) -> bool {
+        let zero = ::na::VectorN::<isize, D>::zeros();
+        v >= &zero && v < &self.size
+    }
+
     fn is_on_border(&self, v: &::na::VectorN<isize, D>) -> bool {
         let one = ::na::VectorN::<isize, D>::from_iterator((1..2).cycle());
         !(v >= &one && v + one < self.size)
     }
 
     fn iterate_maze(&self) -> Vec<::na::VectorN<isize, D>> {
-        Self::iterate_area(&self.size)
+        let mut cells = Self::iterate_area(&self.size);
+        cells.retain(|cell| self.is_in_mask(cell));
+        cells
     }
 
+    /// Cells in grid order, the first axis varying the slowest
     fn iterate_area(size: &::na::VectorN<isize, D>) -> Vec<::na::VectorN<isize, D>> {
         let mut res = vec![];
+        if size.iter().any(|&s| s <= 0) {
+            return res;
+        }
 
-        match D::dim() {
-            2 => for x in 0..size[0] {
-                for y in 0..size[1] {
-                    res.push(Self::new_vec2(x, y));
-                }
-            },
-            3 => for x in 0..size[0] {
-                for y in 0..size[1] {
-                    for z in 0..size[2] {
-                        res.push(Self::new_vec3(x, y, z));
-                    }
+        let mut cell = ::na::VectorN::<isize, D>::zeros();
+        'cells: loop {
+            res.push(cell.clone());
+            for i in (0..D::dim()).rev() {
+                cell[i] += 1;
+                if cell[i] < size[i] {
+                    continue 'cells;
                 }
-            },
-            _ => unimplemented!(),
+                cell[i] = 0;
+            }
+            break;
         }
         res
     }
@@ -487,35 +851,32 @@ where
     where
         F: Fn(&Self, &::na::VectorN<isize, D>) -> bool,
     {
-        let mut unvisited = HashSet::new();
-        for cell in self.iterate_maze() {
-            unvisited.insert(cell);
-        }
-
-        let mut to_visit = HashSet::new();
+        // cells whose filter has been computed
+        let mut visited = ::grid::Grid::new(self.size.clone());
+        let mut to_visit = vec![];
         let mut zones = Vec::new();
 
-        while let Some(cell) = unvisited.pop() {
+        // iterate in grid order so zones are always returned in the same order
+        for cell in self.iterate_maze() {
+            if !visited.insert(&cell) || !filter(&self, &cell) {
+                continue;
+            }
             let mut zone = HashSet::new();
-            to_visit.insert(cell);
+            to_visit.push(cell);
 
             while let Some(cell) = to_visit.pop() {
-                if !filter(&self, &cell) {
-                    continue;
-                }
-
-                for neighbour in self.neighbours.iter().map(|n| n + cell.clone()) {
-                    if unvisited.contains(&neighbour) {
-                        to_visit.insert(neighbour);
+                for neighbour in self.neighbours.iter().map(|n| self.neighbour(&cell, n)) {
+                    if self.is_inside(&neighbour)
+                        && self.is_in_mask(&neighbour)
+                        && visited.insert(&neighbour)
+                        && filter(&self, &neighbour)
+                    {
+                        to_visit.push(neighbour);
                     }
                 }
-
-                unvisited.remove(&cell);
-                assert!(zone.insert(cell));
-            }
-            if !zone.is_empty() {
-                zones.push(zone);
+                zone.insert(cell);
             }
+            zones.push(zone);
         }
 
         zones
@@ -531,18 +892,7 @@ where
     }
 
     pub fn compute_room_zones(&self) -> Vec<HashSet<::na::VectorN<isize, D>>> {
-        self.compute_zones(|maze, cell| {
-            !maze.walls.contains(cell)
-                && maze.openings
-                    .iter()
-                    .filter(|opening| {
-                        opening
-                            .requires
-                            .iter()
-                            .all(|o| !self.walls.contains(&(cell.clone() + o)))
-                    })
-                    .count() > 2
-        })
+        self.compute_zones(|maze, cell| !maze.walls.contains(cell) && maze.free_openings(cell) > 2)
     }
 
     pub fn compute_dead_room_zones(&self) -> Vec<HashSet<::na::VectorN<isize, D>>> {
@@ -551,7 +901,7 @@ where
             let superset = room.iter().fold(HashSet::new(), |mut acc, cell| {
                 self.neighbours
                     .iter()
-                    .map(|n| n + cell)
+                    .map(|n| self.neighbour(cell, n))
                     .filter(|n| !self.walls.contains(n))
                     .for_each(|n| {
                         acc.insert(n);
@@ -564,7 +914,7 @@ where
     }
 
     pub fn compute_corridor_zones(&self) -> Vec<HashSet<::na::VectorN<isize, D>>> {
-        self.compute_zones(|maze, cell| maze.is_corridor(cell))
+        self.compute_zones(|maze, cell| !maze.walls.contains(cell) && maze.free_openings(cell) <= 2)
     }
 
     /// Return all dead room with its entry corridor
@@ -576,7 +926,7 @@ where
                 let superset = room.iter().fold(HashSet::new(), |mut acc, cell| {
                     self.neighbours
                         .iter()
-                        .map(|n| n + cell)
+                        .map(|n| self.neighbour(cell, n))
                         .filter(|n| !self.walls.contains(n))
                         .for_each(|n| {
                             acc.insert(n);
@@ -597,22 +947,10 @@ where
         rooms
     }
 
-    fn new_vec2(x: isize, y: isize) -> ::na::VectorN<isize, D> {
-        let mut v = ::na::VectorN::<isize, D>::zeros();
-        v[0] = x;
-        v[1] = y;
-        v
-    }
-
-    fn new_vec3(x: isize, y: isize, z: isize) -> ::na::VectorN<isize, D> {
-        let mut v = ::na::VectorN::<isize, D>::zeros();
-        v[0] = x;
-        v[1] = y;
-        v[2] = z;
-        v
-    }
-
     /// Only direct openings
+    ///
+    /// Paths stay in the maze and don't go across the faces of wrapped axes, consecutive
+    /// cells are always adjacent
     pub fn find_path_direct(
         &self,
         pos: ::na::VectorN<isize, D>,
@@ -623,12 +961,14 @@ where
             |cell| {
                 let mut res = vec![];
                 for opening in self.openings.iter().filter(|o| o.requires.len() == 1) {
-                    if opening
-                        .requires
-                        .iter()
-                        .all(|o| !self.walls.contains(&(o + cell.clone())))
+                    let next = opening.cell.clone() + cell;
+                    if self.is_inside(&next)
+                        && opening
+                            .requires
+                            .iter()
+                            .all(|o| !self.walls.contains(&(o + cell.clone())))
                     {
-                        res.push((opening.cell.clone() + cell, opening.cost));
+                        res.push((next, opening.cost));
                     }
                 }
                 res
@@ -644,6 +984,32 @@ where
         ).map(|p| p.0)
     }
 
+    /// Cells reachable in one move from the cell and the cost of the move
+    ///
+    /// Cells outside of the maze are not returned
+    pub fn free_successors(
+        &self,
+        cell: &::na::VectorN<isize, D>,
+    ) -> Vec<(::na::VectorN<isize, D>, isize)> {
+        let mut res = vec![];
+        for opening in self.openings.iter() {
+            if opening
+                .requires
+                .iter()
+                .all(|o| !self.walls.contains(&self.neighbour(cell, o)))
+            {
+                let next = self.neighbour(cell, &opening.cell);
+                if self.is_inside(&next) {
+                    res.push((next, opening.cost));
+                }
+            }
+        }
+        res
+    }
+
+    /// Path using all openings, cells outside of the maze are not visited
+    ///
+    /// Paths go across the faces of wrapped axes
     pub fn find_path(
         &self,
         pos: ::na::VectorN<isize, D>,
@@ -651,23 +1017,11 @@ where
     ) -> Option<Vec<::na::VectorN<isize, D>>> {
         ::pathfinding::directed::astar::astar(
             &pos,
+            |cell| self.free_successors(cell),
             |cell| {
-                let mut res = vec![];
-                for opening in self.openings.iter() {
-                    if opening
-                        .requires
-                        .iter()
-                        .all(|o| !self.walls.contains(&(o + cell.clone())))
-                    {
-                        res.push((opening.cell.clone() + cell, opening.cost));
-                    }
-                }
-                res
-            },
-            |cell| {
-                let mut min = (cell[0] - goal[0]).abs();
+                let mut min = self.axis_distance(cell, &goal, 0);
                 for i in 1..D::dim() {
-                    min = min.min((cell[i] - goal[i]).abs());
+                    min = min.min(self.axis_distance(cell, &goal, i));
                 }
                 min * 10
             },
@@ -675,422 +1029,123 @@ where
         ).map(|p| p.0)
     }
 
-    pub fn random_free(&self) -> ::na::VectorN<isize, D> {
-        assert!(self.size.iter().all(|&s| s != 0));
-
-        let ranges: Vec<_> = self.size.iter().map(|&s| Range::new(0, s)).collect();
-        let mut rng = ::rand::thread_rng();
-
-        let mut vec =
-            ::na::VectorN::<isize, D>::from_iterator(ranges.iter().map(|r| r.sample(&mut rng)));
-        while self.walls.contains(&vec) {
-            vec = ::na::VectorN::<isize, D>::from_iterator(
-                ranges.iter().map(|r| r.sample(&mut rng)),
-            );
+    /// Cost of the shortest path from the start to every cell of the maze,
+    /// indexed like `grid::Grid`
+    ///
+    /// Openings are symmetric so it is also the cost from every cell to the start
+    pub fn distances_from(&self, start: &::na::VectorN<isize, D>) -> Vec<Option<isize>> {
+        let grid = ::grid::Grid::new(self.size.clone());
+        let mut distances = vec![None; grid.cell_count()];
+        let start_index = match grid.index(start) {
+            Some(index) => index,
+            None => return distances,
+        };
+
+        let mut heap = BinaryHeap::new();
+        distances[start_index as usize] = Some(0);
+        heap.push(Reverse((0, start_index)));
+        while let Some(Reverse((distance, index))) = heap.pop() {
+            if distances[index as usize].map_or(false, |d| d < distance) {
+                continue;
+            }
+            let cell = grid.cell(index);
+            for (next, cost) in self.free_successors(&cell) {
+                let next_index = grid.index(&next).unwrap();
+                let next_distance = distance + cost;
+                if distances[next_index as usize].map_or(true, |d| next_distance < d) {
+                    distances[next_index as usize] = Some(next_distance);
+                    heap.push(Reverse((next_distance, next_index)));
+                }
+            }
         }
-        vec
+        distances
+    }
+
+    /// Cells of the mask that are not walls, in grid order
+    pub fn free_cells(&self) -> Vec<::na::VectorN<isize, D>> {
+        let mut cells = self.iterate_maze();
+        cells.retain(|cell| !self.walls.contains(cell));
+        cells
     }
 
+    /// Free cells on the surface of the cube of the given radius, clipped to the maze
     pub fn free_in_square(
         &self,
         center: ::na::VectorN<isize, D>,
         radius: isize,
     ) -> Vec<::na::VectorN<isize, D>> {
-        let mut res = vec![];
-
-        let clip_start = center
-            .iter()
-            .map(|c| (c - radius).max(0))
-            .collect::<Vec<_>>();
-
-        let clip_end = center
-            .iter()
-            .zip(self.size.iter())
-            .map(|(c, s)| (c + radius).min(s - 1))
-            .collect::<Vec<_>>();
-
-        match D::dim() {
-            2 => {
-                for y in clip_start[1]..clip_end[1] + 1 {
-                    for &x in [clip_start[0], clip_end[0]].iter() {
-                        let vec = Self::new_vec2(x, y);
-                        if !self.walls.contains(&vec) {
-                            res.push(vec);
-                        }
-                    }
-                }
-                for x in clip_start[0]..clip_end[0] + 1 {
-                    for &y in [clip_start[1], clip_end[1]].iter() {
-                        let vec = Self::new_vec2(x, y);
-                        if !self.walls.contains(&vec) {
-                            res.push(vec);
-                        }
-                    }
-                }
-            }
-            3 => {
-                for x in clip_start[0]..clip_end[0] + 1 {
-                    for y in clip_start[1]..clip_end[1] + 1 {
-                        for &z in [clip_start[2], clip_end[2]].iter() {
-                            let vec = Self::new_vec3(x, y, z);
-                            if !self.walls.contains(&vec) {
-                                res.push(vec);
-                            }
-                        }
-                    }
-                }
-                for y in clip_start[1]..clip_end[1] + 1 {
-                    for z in clip_start[2]..clip_end[2] + 1 {
-                        for &x in [clip_start[0], clip_end[0]].iter() {
-                            let vec = Self::new_vec3(x, y, z);
-                            if !self.walls.contains(&vec) {
-                                res.push(vec);
-                            }
-                        }
-                    }
-                }
-                for x in clip_start[0]..clip_end[0] + 1 {
-                    for z in clip_start[2]..clip_end[2] + 1 {
-                        for &y in [clip_start[1], clip_end[1]].iter() {
-                            let vec = Self::new_vec3(x, y, z);
-                            if !self.walls.contains(&vec) {
-                                res.push(vec);
-                            }
-                        }
-                    }
-                }
-            }
-            _ => unimplemented!(),
-        }
-        res
+        let clip_start = ::na::VectorN::<isize, D>::from_iterator(
+            center.iter().map(|c| (c - radius).max(0)),
+        );
+        let clip_end = ::na::VectorN::<isize, D>::from_iterator(
+            center
+                .iter()
+                .zip(self.size.iter())
+                .map(|(c, s)| (c + radius).min(s - 1)),
+        );
+        let one = ::na::VectorN::<isize, D>::from_element(1);
+
+        Self::iterate_area(&(clip_end.clone() - clip_start.clone() + one))
+            .into_iter()
+            .map(|v| v + clip_start.clone())
+            .filter(|v| {
+                v.iter()
+                    .zip(clip_start.iter().zip(clip_end.iter()))
+                    .any(|(c, (start, end))| c == start || c == end)
+            })
+            .filter(|v| !self.walls.contains(v))
+            .collect()
     }
 
+    /// Face neighbours, two per axis
     fn neighbours() -> Vec<::na::VectorN<isize, D>> {
-        match D::dim() {
-            2 => vec![
-                Self::new_vec2(-1, 0),
-                Self::new_vec2(1, 0),
-                Self::new_vec2(0, -1),
-                Self::new_vec2(0, 1),
-            ],
-            3 => vec![
-                Self::new_vec3(-1, 0, 0),
-                Self::new_vec3(1, 0, 0),
-                Self::new_vec3(0, -1, 0),
-                Self::new_vec3(0, 1, 0),
-                Self::new_vec3(0, 0, -1),
-                Self::new_vec3(0, 0, 1),
-            ],
-            _ => unimplemented!(),
+        let mut res = vec![];
+        for i in 0..D::dim() {
+            for &d in [-1, 1].iter() {
+                let mut v = ::na::VectorN::<isize, D>::zeros();
+                v[i] = d;
+                res.push(v);
+            }
         }
+        res
     }
 
+    /// Every cell of the 3^n cube around the origin except the origin.
+    /// An opening requires all the cells of the cube between the origin and its cell,
+    /// so diagonal moves can't pass between walls.
     fn openings() -> Vec<Opening<D>> {
-        match D::dim() {
-            2 => vec![
-                Opening {
-                    cell: Self::new_vec2(-1, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec2(-1, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec2(1, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec2(1, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec2(0, -1),
-                    cost: 10,
-                    requires: vec![Self::new_vec2(0, -1)],
-                },
-                Opening {
-                    cell: Self::new_vec2(0, 1),
-                    cost: 10,
-                    requires: vec![Self::new_vec2(0, 1)],
-                },
-                Opening {
-                    cell: Self::new_vec2(-1, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec2(-1, 0),
-                        Self::new_vec2(0, -1),
-                        Self::new_vec2(-1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec2(-1, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec2(-1, 0),
-                        Self::new_vec2(0, 1),
-                        Self::new_vec2(-1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec2(1, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec2(1, 0),
-                        Self::new_vec2(0, -1),
-                        Self::new_vec2(1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec2(1, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec2(1, 0),
-                        Self::new_vec2(0, 1),
-                        Self::new_vec2(1, 1),
-                    ],
-                },
-            ],
-            3 => vec![
-                Opening {
-                    cell: Self::new_vec3(-1, 0, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(-1, 0, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 0, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(1, 0, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, -1, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(0, -1, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, 1, 0),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(0, 1, 0)],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, 0, -1),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(0, 0, -1)],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, 0, 1),
-                    cost: 10,
-                    requires: vec![Self::new_vec3(0, 0, 1)],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, -1, 0),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(-1, -1, 0),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, 1, 0),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(-1, 1, 0),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, -1, 0),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(1, -1, 0),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 1, 0),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(1, 1, 0),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, -1, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(0, 0, -1),
-                        Self::new_vec3(0, -1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, -1, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(0, 0, 1),
-                        Self::new_vec3(0, -1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, 1, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(0, 0, -1),
-                        Self::new_vec3(0, 1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(0, 1, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(0, 0, 1),
-                        Self::new_vec3(0, 1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, 0, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, 0, -1),
-                        Self::new_vec3(-1, 0, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, 0, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, 0, 1),
-                        Self::new_vec3(-1, 0, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 0, -1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, 0, -1),
-                        Self::new_vec3(1, 0, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 0, 1),
-                    cost: 15,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, 0, 1),
-                        Self::new_vec3(1, 0, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, -1, -1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(-1, -1, 0),
-                        Self::new_vec3(-1, 0, -1),
-                        Self::new_vec3(0, -1, -1),
-                        Self::new_vec3(-1, -1, -1),
-                        Self::new_vec3(-1, -1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 1, 1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(1, 1, 0),
-                        Self::new_vec3(1, 0, 1),
-                        Self::new_vec3(0, 1, 1),
-                        Self::new_vec3(1, 1, 1),
-                        Self::new_vec3(1, 1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, -1, 1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(-1, -1, 0),
-                        Self::new_vec3(-1, 0, 1),
-                        Self::new_vec3(0, -1, 1),
-                        Self::new_vec3(-1, -1, 1),
-                        Self::new_vec3(-1, -1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, 1, -1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(-1, 1, 0),
-                        Self::new_vec3(-1, 0, -1),
-                        Self::new_vec3(0, 1, -1),
-                        Self::new_vec3(-1, 1, -1),
-                        Self::new_vec3(-1, 1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, -1, -1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(1, -1, 0),
-                        Self::new_vec3(1, 0, -1),
-                        Self::new_vec3(0, -1, -1),
-                        Self::new_vec3(1, -1, -1),
-                        Self::new_vec3(1, -1, -1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(-1, 1, 1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(-1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(-1, 1, 0),
-                        Self::new_vec3(-1, 0, 1),
-                        Self::new_vec3(0, 1, 1),
-                        Self::new_vec3(-1, 1, 1),
-                        Self::new_vec3(-1, 1, 1),
-                    ],
-                },
-                Opening {
-                    cell: Self::new_vec3(1, 1, -1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, 1, 0),
-                        Self::new_vec3(1, 1, 0),
-                        Self::new_vec3(1, 0, -1),
-                        Self::new_vec3(0, 1, -1),
-                        Self::new_vec3(1, 1, -1),
-                        Self::new_vec3(1, 1, -1),
-                    ],
-                },
+        let three = ::na::VectorN::<isize, D>::from_element(3);
+        let one = ::na::VectorN::<isize, D>::from_element(1);
+        let offsets = Self::iterate_area(&three)
+            .into_iter()
+            .map(|v| v - one.clone())
+            .filter(|v| v.iter().any(|&c| c != 0))
+            .collect::<Vec<_>>();
+
+        let mut openings = offsets
+            .iter()
+            .map(|cell| {
+                let requires = offsets
+                    .iter()
+                    .filter(|o| o.iter().zip(cell.iter()).all(|(&o, &c)| o == 0 || o == c))
+                    .cloned()
+                    .collect();
+                let diagonal = cell.iter().filter(|&&c| c != 0).count();
+                let cost = match diagonal {
+                    1 => 10,
+                    2 => 15,
+                    3 => 17,
+                    _ => (10.0 * (diagonal as f64).sqrt()).round() as isize,
+                };
                 Opening {
-                    cell: Self::new_vec3(1, -1, 1),
-                    cost: 17,
-                    requires: vec![
-                        Self::new_vec3(1, 0, 0),
-                        Self::new_vec3(0, -1, 0),
-                        Self::new_vec3(1, -1, 0),
-                        Self::new_vec3(1, 0, 1),
-                        Self::new_vec3(0, -1, 1),
-                        Self::new_vec3(1, -1, 1),
-                        Self::new_vec3(1, -1, 1),
-                    ],
-                },
-            ],
-            _ => unimplemented!(),
-        }
+                    cell: cell.clone(),
+                    requires,
+                    cost,
+                }
+            })
+            .collect::<Vec<_>>();
+        openings.sort_by_key(|opening| opening.cost);
+        openings
     }
 }
 
@@ -1111,6 +1166,186 @@ impl ::std::fmt::Display for Maze<::na::U2> {
     }
 }
 
+/// Entity marker in the layered text format
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    Spawn,
+    Target,
+    Mine,
+    RocketLauncher,
+}
+
+impl Marker {
+    pub fn to_char(&self) -> char {
+        match *self {
+            Marker::Spawn => 'S',
+            Marker::Target => 'T',
+            Marker::Mine => 'M',
+            Marker::RocketLauncher => 'L',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'S' => Some(Marker::Spawn),
+            'T' => Some(Marker::Target),
+            'M' => Some(Marker::Mine),
+            'L' => Some(Marker::RocketLauncher),
+            _ => None,
+        }
+    }
+}
+
+/// Layered text format: one block of rows for each z, each preceded by a line starting with `--`
+///
+/// `#` is a wall, `.` is free and markers are free cells holding an entity:
+/// ```text
+/// -- z 0
+/// ###
+/// #S#
+/// ###
+/// -- z 1
+/// ###
+/// #.T
+/// ###
+/// ```
+impl Maze<::na::U3> {
+    /// Markers outside of the maze are ignored
+    pub fn to_layers(&self, markers: &[(Marker, ::na::Vector3<isize>)]) -> String {
+        let mut text = String::new();
+        for z in 0..self.size[2] {
+            text.push_str(&format!("-- z {}\n", z));
+            for y in 0..self.size[1] {
+                for x in 0..self.size[0] {
+                    let cell = ::na::Vector3::new(x, y, z);
+                    if self.walls.contains(&cell) {
+                        text.push('#');
+                    } else if let Some(&(marker, _)) = markers.iter().find(|m| m.1 == cell) {
+                        text.push(marker.to_char());
+                    } else {
+                        text.push('.');
+                    }
+                }
+                text.push('\n');
+            }
+        }
+        text
+    }
+
+    pub fn from_layers(
+        text: &str,
+    ) -> Result<(Self, Vec<(Marker, ::na::Vector3<isize>)>), ::failure::Error> {
+        let mut layers: Vec<Vec<(usize, &str)>> = vec![];
+        let mut new_layer = true;
+        for (number, line) in text.lines().enumerate() {
+            if line.starts_with("--") {
+                new_layer = true;
+                continue;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if new_layer {
+                layers.push(vec![]);
+                new_layer = false;
+            }
+            layers.last_mut().unwrap().push((number + 1, line));
+        }
+
+        if layers.is_empty() {
+            return Err(::failure::err_msg("maze has no layer"));
+        }
+
+        let width = layers[0][0].1.chars().count();
+        let height = layers[0].len();
+
+        let mut maze = Self::new_rectangle(::na::Vector3::new(
+            width as isize,
+            height as isize,
+            layers.len() as isize,
+        ));
+        let mut markers = vec![];
+
+        for (z, layer) in layers.iter().enumerate() {
+            if layer.len() != height {
+                return Err(::failure::err_msg(format!(
+                    "layer {} has {} rows instead of {}",
+                    z,
+                    layer.len(),
+                    height
+                )));
+            }
+            for (y, &(number, line)) in layer.iter().enumerate() {
+                if line.chars().count() != width {
+                    return Err(::failure::err_msg(format!(
+                        "line {} has {} cells instead of {}",
+                        number,
+                        line.chars().count(),
+                        width
+                    )));
+                }
+                for (x, c) in line.chars().enumerate() {
+                    let cell = ::na::Vector3::new(x as isize, y as isize, z as isize);
+                    match c {
+                        '#' => {
+                            maze.walls.insert(&cell);
+                        }
+                        '.' => (),
+                        _ => match Marker::from_char(c) {
+                            Some(marker) => markers.push((marker, cell)),
+                            None => {
+                                return Err(::failure::err_msg(format!(
+                                    "line {}: invalid cell '{}'",
+                                    number, c
+                                )))
+                            }
+                        },
+                    }
+                }
+            }
+        }
+
+        Ok((maze, markers))
+    }
+}
+
+impl ::std::fmt::Display for Maze<::na::U3> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "{}", self.to_layers(&[]))
+    }
+}
+
+impl Maze<::na::U4> {
+    /// 3D maze at position `w` on the last axis
+    pub fn slice(&self, w: isize) -> Maze<::na::U3> {
+        let mut maze = Maze::new_rectangle(::na::Vector3::new(self.size[0], self.size[1], self.size[2]));
+        let walls = self.walls
+            .iter()
+            .filter(|wall| wall[3] == w)
+            .map(|wall| ::na::Vector3::new(wall[0], wall[1], wall[2]));
+        maze.walls.extend(walls);
+        maze
+    }
+}
+
+#[test]
+fn layers_round_trip() {
+    let text = "\
+-- z 0
+###
+#S#
+###
+-- z 1
+#M#
+#.T
+#L#
+";
+    let (maze, markers) = Maze::from_layers(text).unwrap();
+    assert_eq!(maze.size(), ::na::Vector3::new(3, 3, 2));
+    assert_eq!(markers.len(), 4);
+    assert_eq!(maze.to_layers(&markers), text);
+}
+
 // pub fn find_path(
 //     &self,
 //     pos: ::na::Vector3<f32>,
@@ -1133,3 +1368,188 @@ impl ::std::fmt::Display for Maze<::na::U2> {
 //     }
 //     outer
 // }
+
+#[test]
+fn kruskal_is_reproducible() {
+    let size = ::na::Vector3::new(9, 9, 9);
+    let generate = |seed| {
+        let mut rng = ::util::seeded_rng(seed);
+        let mut maze = Maze::new_kruskal(size, 5.0, ::na::zero(), &mut rng);
+        maze.reduce(1);
+        maze.circle();
+        maze.fill_smallests();
+        maze
+    };
+    assert_eq!(generate(42).walls, generate(42).walls);
+}
+
+#[cfg(test)]
+fn assert_openings<D>(maze: &Maze<D>, table: &[(&[isize], isize, &[&[isize]])])
+where
+    D: ::na::Dim + ::na::DimName + Hash,
+    D::Value: Mul<::typenum::UInt<::typenum::UTerm, ::typenum::B1>, Output = D::Value>
+        + ::generic_array::ArrayLength<isize>,
+{
+    let to_vec = |v: &[isize]| ::na::VectorN::<isize, D>::from_row_slice(v);
+    assert_eq!(maze.openings.len(), table.len());
+    for &(cell, cost, requires) in table {
+        let opening = maze.openings
+            .iter()
+            .find(|opening| opening.cell == to_vec(cell))
+            .unwrap();
+        assert_eq!(opening.cost, cost);
+        let expected = requires.iter().map(|r| to_vec(r)).collect::<HashSet<_>>();
+        let generated = opening.requires.iter().cloned().collect::<HashSet<_>>();
+        assert_eq!(generated, expected, "opening {:?}", cell);
+    }
+}
+
+#[test]
+fn generated_openings_match_tables() {
+    assert_openings(&Maze::<::na::U2>::new_empty(), &[
+        (&[-1, 0], 10, &[&[-1, 0]]),
+        (&[1, 0], 10, &[&[1, 0]]),
+        (&[0, -1], 10, &[&[0, -1]]),
+        (&[0, 1], 10, &[&[0, 1]]),
+        (&[-1, -1], 15, &[&[-1, 0], &[0, -1], &[-1, -1]]),
+        (&[-1, 1], 15, &[&[-1, 0], &[0, 1], &[-1, 1]]),
+        (&[1, -1], 15, &[&[1, 0], &[0, -1], &[1, -1]]),
+        (&[1, 1], 15, &[&[1, 0], &[0, 1], &[1, 1]]),
+    ]);
+
+    // the hand written corners missed their face on the last axis
+    assert_openings(&Maze::<::na::U3>::new_empty(), &[
+        (&[-1, 0, 0], 10, &[&[-1, 0, 0]]),
+        (&[1, 0, 0], 10, &[&[1, 0, 0]]),
+        (&[0, -1, 0], 10, &[&[0, -1, 0]]),
+        (&[0, 1, 0], 10, &[&[0, 1, 0]]),
+        (&[0, 0, -1], 10, &[&[0, 0, -1]]),
+        (&[0, 0, 1], 10, &[&[0, 0, 1]]),
+        (&[-1, -1, 0], 15, &[&[-1, 0, 0], &[0, -1, 0], &[-1, -1, 0]]),
+        (&[-1, 1, 0], 15, &[&[-1, 0, 0], &[0, 1, 0], &[-1, 1, 0]]),
+        (&[1, -1, 0], 15, &[&[1, 0, 0], &[0, -1, 0], &[1, -1, 0]]),
+        (&[1, 1, 0], 15, &[&[1, 0, 0], &[0, 1, 0], &[1, 1, 0]]),
+        (&[0, -1, -1], 15, &[&[0, -1, 0], &[0, 0, -1], &[0, -1, -1]]),
+        (&[0, -1, 1], 15, &[&[0, -1, 0], &[0, 0, 1], &[0, -1, 1]]),
+        (&[0, 1, -1], 15, &[&[0, 1, 0], &[0, 0, -1], &[0, 1, -1]]),
+        (&[0, 1, 1], 15, &[&[0, 1, 0], &[0, 0, 1], &[0, 1, 1]]),
+        (&[-1, 0, -1], 15, &[&[-1, 0, 0], &[0, 0, -1], &[-1, 0, -1]]),
+        (&[-1, 0, 1], 15, &[&[-1, 0, 0], &[0, 0, 1], &[-1, 0, 1]]),
+        (&[1, 0, -1], 15, &[&[1, 0, 0], &[0, 0, -1], &[1, 0, -1]]),
+        (&[1, 0, 1], 15, &[&[1, 0, 0], &[0, 0, 1], &[1, 0, 1]]),
+        (&[-1, -1, -1], 17, &[&[-1, 0, 0], &[0, -1, 0], &[0, 0, -1], &[-1, -1, 0], &[-1, 0, -1], &[0, -1, -1], &[-1, -1, -1]]),
+        (&[1, 1, 1], 17, &[&[1, 0, 0], &[0, 1, 0], &[0, 0, 1], &[1, 1, 0], &[1, 0, 1], &[0, 1, 1], &[1, 1, 1]]),
+        (&[-1, -1, 1], 17, &[&[-1, 0, 0], &[0, -1, 0], &[0, 0, 1], &[-1, -1, 0], &[-1, 0, 1], &[0, -1, 1], &[-1, -1, 1]]),
+        (&[-1, 1, -1], 17, &[&[-1, 0, 0], &[0, 1, 0], &[0, 0, -1], &[-1, 1, 0], &[-1, 0, -1], &[0, 1, -1], &[-1, 1, -1]]),
+        (&[1, -1, -1], 17, &[&[1, 0, 0], &[0, -1, 0], &[0, 0, -1], &[1, -1, 0], &[1, 0, -1], &[0, -1, -1], &[1, -1, -1]]),
+        (&[-1, 1, 1], 17, &[&[-1, 0, 0], &[0, 1, 0], &[0, 0, 1], &[-1, 1, 0], &[-1, 0, 1], &[0, 1, 1], &[-1, 1, 1]]),
+        (&[1, 1, -1], 17, &[&[1, 0, 0], &[0, 1, 0], &[0, 0, -1], &[1, 1, 0], &[1, 0, -1], &[0, 1, -1], &[1, 1, -1]]),
+        (&[1, -1, 1], 17, &[&[1, 0, 0], &[0, -1, 0], &[0, 0, 1], &[1, -1, 0], &[1, 0, 1], &[0, -1, 1], &[1, -1, 1]]),
+    ]);
+}
+
+#[test]
+fn four_dimensional_kruskal() {
+    let size = ::na::Vector4::new(5, 5, 5, 5);
+    let maze = Maze::new_kruskal(size, 100.0, ::na::zero(), &mut ::util::seeded_rng(0));
+    assert_eq!(maze.neighbours.len(), 8);
+    assert_eq!(maze.openings.len(), 80);
+    assert!(!maze.walls.is_empty());
+
+    let slice = maze.slice(2);
+    assert_eq!(slice.size(), ::na::Vector3::new(5, 5, 5));
+    assert_eq!(slice.walls.len(), maze.walls.iter().filter(|w| w[3] == 2).count());
+}
+
+/// Maze of the size full of walls but the free cells
+#[cfg(test)]
+pub fn walled<F>(size: ::na::Vector3<isize>, free: F) -> Maze<::na::U3>
+where
+    F: Fn(&::na::Vector3<isize>) -> bool,
+{
+    let mut maze = Maze::new_rectangle(size);
+    maze.walls.extend(Maze::<::na::U3>::iterate_area(&size).into_iter().filter(|cell| !free(cell)));
+    maze
+}
+
+#[test]
+fn wrapped_axis_joins_opposite_faces() {
+    let mut maze = Maze::new_rectangle(::na::Vector3::new(7, 3, 3));
+    for x in 0..7 {
+        for y in 0..3 {
+            for z in 0..3 {
+                if y != 1 || z != 1 || x == 3 {
+                    maze.walls.insert(&::na::Vector3::new(x, y, z));
+                }
+            }
+        }
+    }
+    let start = ::na::Vector3::new(1, 1, 1);
+    let goal = ::na::Vector3::new(5, 1, 1);
+    assert!(maze.find_path(start, goal).is_none());
+    assert_eq!(maze.compute_zones(|maze, cell| !maze.walls.contains(cell)).len(), 2);
+
+    maze.set_wrap(0, true);
+    assert_eq!(maze.find_path(start, goal).unwrap().len(), 5);
+    assert_eq!(maze.compute_zones(|maze, cell| !maze.walls.contains(cell)).len(), 1);
+}
+
+#[test]
+fn distances_follow_corridors() {
+    let mut maze = Maze::new_rectangle(::na::Vector3::new(5, 3, 3));
+    for x in 0..5 {
+        for y in 0..3 {
+            for z in 0..3 {
+                if y != 1 || z != 1 {
+                    maze.walls.insert(&::na::Vector3::new(x, y, z));
+                }
+            }
+        }
+    }
+    let grid = ::grid::Grid::new(maze.size());
+    let distances = maze.distances_from(&::na::Vector3::new(0, 1, 1));
+    for x in 0..5 {
+        let index = grid.index(&::na::Vector3::new(x, 1, 1)).unwrap() as usize;
+        assert_eq!(distances[index], Some(x * 10));
+    }
+    let wall = grid.index(&::na::Vector3::new(0, 0, 0)).unwrap() as usize;
+    assert_eq!(distances[wall], None);
+}
+
+#[test]
+fn wall_cuboids_cover_walls() {
+    let mut maze = Maze::new_kruskal(::na::Vector3::new(11, 9, 7), 5.0, ::na::zero(), &mut ::util::seeded_rng(0));
+    maze.reduce(1);
+    let cuboids = maze.wall_cuboids();
+    assert!(cuboids.len() < maze.walls.len());
+
+    let mut covered = HashSet::new();
+    for &(start, size) in &cuboids {
+        for cell in Maze::iterate_area(&size) {
+            // no overlap
+            assert!(covered.insert(cell + start));
+        }
+    }
+    assert_eq!(covered, maze.walls.iter().collect::<HashSet<_>>());
+}
+
+#[test]
+fn adjacent_wall_regions_contrast() {
+    // three wall slabs, the middle one faces the two others
+    let mut maze = Maze::new_rectangle(::na::Vector3::new(5, 1, 3));
+    for &x in &[0, 2, 4] {
+        for z in 0..3 {
+            maze.walls.insert(&::na::Vector3::new(x, 0, z));
+        }
+    }
+    let colors = maze.build_colors(2, &mut ::util::seeded_rng(0));
+    let color = |x| {
+        let color = colors[&::na::Vector3::new(x, 0, 0)];
+        for z in 0..3 {
+            assert_eq!(colors[&::na::Vector3::new(x, 0, z)], color);
+        }
+        color
+    };
+    assert!(color(0) != color(2));
+    assert!(color(2) != color(4));
+}
@@ -0,0 +1,250 @@
+//! Hash based implementations of maze operations, kept to check and benchmark the ones using
+//! dense grids
+//!
+//! Run the benchmarks with `cargo test --release bench -- --ignored --nocapture`
+
+use maze::Maze;
+use rand::distributions::{Distribution, Range};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+
+type Cell = ::na::Vector3<isize>;
+
+/// Kruskal rewriting the group of every cell on each merge
+fn kruskal_reference<R: ::rand::Rng>(size: Cell, percent: f64, bug: Cell, rng: &mut R) -> HashSet<Cell> {
+    struct GridCell {
+        wall: bool,
+        group: usize,
+    }
+
+    let mut grid = HashMap::new();
+    let mut i = 0;
+    for x in 0..size[0] {
+        for y in 0..size[1] {
+            for z in 0..size[2] {
+                grid.insert(Cell::new(x, y, z), GridCell { wall: false, group: i });
+                i += 1;
+            }
+        }
+    }
+
+    let mut x_wall = vec![];
+    let mut y_wall = vec![];
+    let mut z_wall = vec![];
+    for i in -1..2 {
+        for j in -1..2 {
+            x_wall.push(Cell::new(0, i, j));
+            y_wall.push(Cell::new(i, 0, j));
+            z_wall.push(Cell::new(i, j, 0));
+        }
+    }
+
+    let mut walls: Vec<Vec<Cell>> = vec![];
+    for x in 1..size[0] / 2 + 1 {
+        for y in 1..size[1] / 2 + 1 {
+            for z in 1..size[2] / 2 + 1 {
+                let x_end = x == size[0] / 2;
+                let y_end = y == size[1] / 2;
+                let z_end = z == size[2] / 2;
+                if !y_end && !z_end {
+                    let p = Cell::new(x * 2 - 1 + bug[0], y * 2, z * 2);
+                    walls.push(x_wall.iter().map(|c| c + p).collect());
+                }
+                if !x_end && !z_end {
+                    let p = Cell::new(x * 2, y * 2 - 1 + bug[1], z * 2);
+                    walls.push(y_wall.iter().map(|c| c + p).collect());
+                }
+                if !x_end && !y_end {
+                    let p = Cell::new(x * 2, y * 2, z * 2 - 1 + bug[2]);
+                    walls.push(z_wall.iter().map(|c| c + p).collect());
+                }
+            }
+        }
+    }
+
+    let stop = ((walls.len() as f64) * (1. - percent / 100.)) as usize;
+    while walls.len() > stop {
+        let i = Range::new(0, walls.len()).sample(rng);
+        let wall = walls.swap_remove(i);
+
+        let groups = wall.iter().map(|cell| grid[cell].group).collect::<HashSet<_>>();
+        let one_group = grid[&wall[0]].group;
+        if groups.len() > 2 {
+            for cell in &wall {
+                grid.get_mut(cell).unwrap().wall = true;
+            }
+            for cell in grid.values_mut() {
+                if groups.contains(&cell.group) {
+                    cell.group = one_group;
+                }
+            }
+        }
+    }
+
+    grid.into_iter()
+        .filter(|&(_, ref cell)| cell.wall)
+        .map(|(cell, _)| cell)
+        .collect()
+}
+
+/// Flood fill with hash sets
+fn zones_reference<F: Fn(&Cell) -> bool>(maze: &Maze<::na::U3>, filter: F) -> Vec<HashSet<Cell>> {
+    let size = maze.size();
+    let mut unvisited = HashSet::new();
+    for x in 0..size[0] {
+        for y in 0..size[1] {
+            for z in 0..size[2] {
+                unvisited.insert(Cell::new(x, y, z));
+            }
+        }
+    }
+
+    let mut zones = vec![];
+    while let Some(start) = unvisited.iter().next().cloned() {
+        unvisited.remove(&start);
+        if !filter(&start) {
+            continue;
+        }
+        let mut zone = HashSet::new();
+        let mut to_visit = vec![start];
+        while let Some(cell) = to_visit.pop() {
+            for n in &maze.neighbours {
+                let n = n + cell;
+                if unvisited.contains(&n) && filter(&n) {
+                    unvisited.remove(&n);
+                    to_visit.push(n);
+                }
+            }
+            zone.insert(cell);
+        }
+        zones.push(zone);
+    }
+    zones
+}
+
+fn room_zones_reference(maze: &Maze<::na::U3>) -> Vec<HashSet<Cell>> {
+    zones_reference(maze, |cell| !maze.walls.contains(cell) && !maze.is_corridor(cell))
+}
+
+fn fill_dead_corridors_reference(maze: &mut Maze<::na::U3>) {
+    loop {
+        let mut corridors = zones_reference(maze, |cell| maze.is_corridor(cell));
+        corridors.retain(|corridor| {
+            corridor.iter().any(|cell| {
+                let neighbours_wall = maze.neighbours
+                    .iter()
+                    .filter(|n| maze.walls.contains(&(*n + cell)))
+                    .count();
+                neighbours_wall >= maze.neighbours.len() - 1
+            })
+        });
+        if corridors.is_empty() {
+            break;
+        }
+        for cell in corridors.iter().flat_map(|z| z) {
+            maze.walls.insert(cell);
+        }
+    }
+}
+
+fn sorted(zones: Vec<HashSet<Cell>>) -> Vec<Vec<[isize; 3]>> {
+    let mut zones = zones
+        .into_iter()
+        .map(|zone| {
+            let mut zone = zone.iter().map(|c| [c[0], c[1], c[2]]).collect::<Vec<_>>();
+            zone.sort();
+            zone
+        })
+        .collect::<Vec<_>>();
+    zones.sort();
+    zones
+}
+
+fn kruskal(size: Cell) -> Maze<::na::U3> {
+    Maze::new_kruskal(size, 5.0, ::na::zero(), &mut ::util::seeded_rng(0))
+}
+
+#[test]
+fn dense_implementations_match_references() {
+    let size = Cell::new(15, 13, 11);
+    let mut maze = kruskal(size);
+    let reference = kruskal_reference(size, 5.0, ::na::zero(), &mut ::util::seeded_rng(0));
+    assert_eq!(maze.walls.iter().collect::<HashSet<_>>(), reference);
+
+    assert_eq!(sorted(maze.compute_room_zones()), sorted(room_zones_reference(&maze)));
+
+    let mut reference = maze.clone();
+    maze.fill_dead_corridors();
+    fill_dead_corridors_reference(&mut reference);
+    assert_eq!(maze.walls, reference.walls);
+}
+
+fn time<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    let start = Instant::now();
+    let res = f();
+    let elapsed = start.elapsed();
+    println!(
+        "{:<32} {:>8.3} s",
+        name,
+        elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 * 1e-9
+    );
+    res
+}
+
+#[test]
+#[ignore]
+fn bench_half_size_30() {
+    let size = Cell::new(61, 61, 61);
+
+    let maze = time("new_kruskal", || kruskal(size));
+    time("new_kruskal reference", || {
+        kruskal_reference(size, 5.0, ::na::zero(), &mut ::util::seeded_rng(0))
+    });
+
+    time("compute_room_zones", || maze.compute_room_zones());
+    time("compute_room_zones reference", || room_zones_reference(&maze));
+
+    time("fill_dead_corridors", || maze.clone().fill_dead_corridors());
+    time("fill_dead_corridors reference", || {
+        fill_dead_corridors_reference(&mut maze.clone())
+    });
+}
+
+/// Time physic steps with a ball rolling in every room
+fn step_world(name: &str, maze: &Maze<::na::U3>, walls: &[(Cell, Cell)]) {
+    let mut world = ::resource::PhysicWorld::new();
+    world.set_gravity(::na::Vector3::new(0.0, 0.0, -10.0));
+    for &(start, size) in walls {
+        let half_extents = size.map(|s| s as f32) / 2.0;
+        let shape = ::ncollide::shape::Cuboid3::new(half_extents);
+        let mut body = ::nphysics::object::RigidBody::new_static(shape, 0.0, 0.0);
+        let position = start.map(|s| s as f32) + half_extents;
+        body.set_transformation(::na::Isometry3::new(position, ::na::zero()));
+        world.add_rigid_body(body);
+    }
+    for room in maze.compute_room_zones() {
+        let cell = room.iter().next().unwrap().map(|s| s as f32) + ::na::Vector3::from_element(0.5);
+        let shape = ::ncollide::shape::Ball::new(0.3);
+        let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+        body.set_transformation(::na::Isometry3::new(cell, ::na::zero()));
+        world.add_rigid_body(body);
+    }
+    time(name, || {
+        for _ in 0..100 {
+            world.step(1.0 / 60.0);
+        }
+    });
+}
+
+#[test]
+#[ignore]
+fn bench_wall_colliders() {
+    let mut maze = kruskal(Cell::new(21, 21, 21));
+    maze.reduce(1);
+
+    let cells = maze.walls.iter().map(|cell| (cell, Cell::from_element(1))).collect::<Vec<_>>();
+    let cuboids = maze.wall_cuboids();
+    println!("{} wall cells, {} cuboids", cells.len(), cuboids.len());
+    step_world("100 steps one collider per cell", &maze, &cells);
+    step_world("100 steps merged colliders", &maze, &cuboids);
+}
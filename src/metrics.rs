@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+/// Measures of a maze used to estimate the difficulty of a level
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeMetrics {
+    /// Number of steps from the spawn to each target, `None` if unreachable
+    pub target_distances: Vec<Option<usize>>,
+    pub free_cells: usize,
+    pub rooms: usize,
+    pub corridors: usize,
+    /// Free cells with only one free neighbour
+    pub dead_ends: usize,
+    /// Average number of free neighbours of junction cells
+    pub branching_factor: f64,
+    /// Average number of cells in a corridor zone
+    pub average_corridor_length: f64,
+    /// Average ratio between path length and straight distance to targets
+    pub tortuosity: f64,
+}
+
+impl MazeMetrics {
+    /// Paths can go around the maze like players do
+    pub fn compute(
+        maze: &::maze::Maze<::na::U3>,
+        spawn: ::na::Vector3<isize>,
+        targets: &[::na::Vector3<isize>],
+    ) -> Self {
+        let free: HashSet<_> = maze.compute_zones(|maze, cell| !maze.walls.contains(cell))
+            .into_iter()
+            .flat_map(|zone| zone)
+            .collect();
+
+        let degree = |cell: &::na::Vector3<isize>| {
+            maze.neighbours
+                .iter()
+                .map(|n| n + cell)
+                .filter(|n| !maze.walls.contains(n))
+                .count()
+        };
+        let dead_ends = free.iter().filter(|cell| degree(cell) == 1).count();
+        let junctions = free.iter()
+            .map(|cell| degree(cell))
+            .filter(|&d| d > 2)
+            .collect::<Vec<_>>();
+        let branching_factor = mean(junctions.iter().map(|&d| d as f64));
+
+        let rooms = maze.compute_room_zones().len();
+        let corridor_zones = maze.compute_corridor_zones();
+        let average_corridor_length = mean(corridor_zones.iter().map(|zone| zone.len() as f64));
+
+        let mut extended = maze.clone();
+        let shift = extended.extend(1);
+
+        let mut target_distances = vec![];
+        let mut ratios = vec![];
+        for target in targets {
+            let path = extended.find_path(spawn + shift, target + shift);
+            target_distances.push(path.as_ref().map(|path| path.len() - 1));
+            if let Some(path) = path {
+                let straight = (target - spawn).map(|c| c as f64).norm();
+                if straight > 0.0 {
+                    let length = path.windows(2)
+                        .map(|step| (step[1] - step[0]).map(|c| c as f64).norm())
+                        .sum::<f64>();
+                    ratios.push(length / straight);
+                }
+            }
+        }
+
+        MazeMetrics {
+            target_distances,
+            free_cells: free.len(),
+            rooms,
+            corridors: corridor_zones.len(),
+            dead_ends,
+            branching_factor,
+            average_corridor_length,
+            tortuosity: if ratios.is_empty() { 1.0 } else { mean(ratios.into_iter()) },
+        }
+    }
+
+    /// Average distance to reachable targets
+    pub fn average_target_distance(&self) -> f64 {
+        mean(self.target_distances.iter().filter_map(|d| *d).map(|d| d as f64))
+    }
+
+    /// Estimation between 0.0 (trivial) and 1.0 (hardest) of the maze only
+    pub fn difficulty(&self) -> f64 {
+        let distance = (self.average_target_distance() / 60.0).min(1.0);
+        let tortuosity = ((self.tortuosity - 1.0) / 2.0).min(1.0).max(0.0);
+        let dead_ends = if self.free_cells == 0 {
+            0.0
+        } else {
+            (10.0 * self.dead_ends as f64 / self.free_cells as f64).min(1.0)
+        };
+        let corridors = (self.average_corridor_length / 20.0).min(1.0);
+        0.4 * distance + 0.3 * tortuosity + 0.15 * dead_ends + 0.15 * corridors
+    }
+}
+
+fn mean<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+#[test]
+fn straight_corridor_metrics() {
+    let mut maze = ::maze::Maze::new_rectangle(::na::Vector3::new(7, 3, 3));
+    for x in 0..7 {
+        for y in 0..3 {
+            for z in 0..3 {
+                if y != 1 || z != 1 {
+                    maze.walls.insert(&::na::Vector3::new(x, y, z));
+                }
+            }
+        }
+    }
+    let metrics = MazeMetrics::compute(
+        &maze,
+        ::na::Vector3::new(0, 1, 1),
+        &[::na::Vector3::new(6, 1, 1)],
+    );
+    assert_eq!(metrics.target_distances, vec![Some(6)]);
+    assert_eq!(metrics.free_cells, 7);
+    assert_eq!(metrics.rooms, 0);
+    assert_eq!(metrics.corridors, 1);
+    assert_eq!(metrics.tortuosity, 1.0);
+}
@@ -61,25 +61,27 @@ const TILE: [Vertex; 36] = [
     Vertex { position: [1.0, 1.0, -0.05], tex_coords: [1.0, 0.6] },
 ];
 
-pub fn generate_tile(width: isize, height: isize) -> Vec<Vertex> {
+/// Tile of the size in cells divided by the subdivision
+pub fn generate_tile(size: ::tile::TileSize, subdivision: isize) -> Vec<Vertex> {
+    let width = size.width() as f32 / subdivision as f32;
+    let height = size.height() as f32 / subdivision as f32;
     TILE.iter()
         .cloned()
         .map(|mut vertex| {
             if vertex.tex_coords[0] == 0.6 {
-                vertex.tex_coords[0] = 0.5 + THICKNESS / width as f32;
+                vertex.tex_coords[0] = 0.5 + THICKNESS / width;
             }
             if vertex.tex_coords[0] == 0.7 {
-                vertex.tex_coords[0] = 0.5 + THICKNESS * 2.0 / width as f32;
+                vertex.tex_coords[0] = 0.5 + THICKNESS * 2.0 / width;
             }
             if vertex.tex_coords[1] == 0.6 {
-                vertex.tex_coords[1] = 0.5 + THICKNESS / height as f32;
+                vertex.tex_coords[1] = 0.5 + THICKNESS / height;
             }
             if vertex.tex_coords[1] == 0.7 {
-                vertex.tex_coords[1] = 0.5 + THICKNESS * 2.0 / height as f32;
+                vertex.tex_coords[1] = 0.5 + THICKNESS * 2.0 / height;
             }
-            // IDEA: unaligned tiles can be cool
-            vertex.position[0] *= width as f32 / 2.0;
-            vertex.position[1] *= height as f32 / 2.0;
+            vertex.position[0] *= width / 2.0;
+            vertex.position[1] *= height / 2.0;
 
             vertex.position[0] = vertex.position[0] - vertex.position[0].signum() * 0.05;
             vertex.position[1] = vertex.position[1] - vertex.position[1].signum() * 0.05;
@@ -90,3 +92,32 @@ pub fn generate_tile(width: isize, height: isize) -> Vec<Vertex> {
         })
         .collect::<Vec<_>>()
 }
+
+/// Cuboid of each arm of the tube piece, matching its collision shapes
+pub fn generate_tube(shape: ::tube::Shape) -> Vec<Vertex> {
+    let mut vertices = vec![];
+    for (position, half_extents) in shape.arm_cuboids() {
+        for axis in 0..3 {
+            let u = (axis + 1) % 3;
+            let v = (axis + 2) % 3;
+            for &side in &[-1.0, 1.0] {
+                let corner = |a: f32, b: f32| {
+                    let mut p = ::na::Vector3::zeros();
+                    p[axis] = side * half_extents[axis];
+                    p[u] = a * half_extents[u];
+                    p[v] = b * half_extents[v];
+                    let p = position * ::na::Point3::from_coordinates(p);
+                    Vertex::new([p[0], p[1], p[2]], [(a + 1.0) / 2.0, (b + 1.0) / 2.0])
+                };
+                // counter clockwise seen from outside
+                let quad = if side > 0.0 {
+                    [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+                } else {
+                    [(-1.0, -1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+                };
+                vertices.extend(quad.iter().map(|&(a, b)| corner(a, b)));
+            }
+        }
+    }
+    vertices
+}
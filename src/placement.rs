@@ -0,0 +1,375 @@
+use rand::Rng;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Maximum number of random cells tried for one entity
+const TRIES: usize = 500;
+/// Maximum number of cells looked through for line of sight
+const SIGHT: isize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Zone {
+    Any,
+    Room,
+    Corridor,
+    /// Room with one way in or end of a dead end corridor
+    DeadEnd,
+    /// Room the farthest from the first spawn
+    FarthestRoom,
+    /// Rooms on the shortest path from the first spawn to the farthest room
+    CriticalPath,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlacementRule {
+    /// Minimum number of moves from the closest spawn, ignored for spawns
+    pub min_spawn_distance: usize,
+    pub zone: Zone,
+    /// Minimum distance in cells to other spawns for spawns, to mines and rocket launchers
+    /// otherwise
+    pub min_spacing: f32,
+    /// A corridor must be visible along an axis
+    pub corridor_line_of_sight: bool,
+}
+
+impl Default for PlacementRule {
+    fn default() -> Self {
+        PlacementRule {
+            min_spawn_distance: 0,
+            zone: Zone::Any,
+            min_spacing: 0.0,
+            corridor_line_of_sight: false,
+        }
+    }
+}
+
+/// Rules of each kind of entity
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlacementRules {
+    pub spawn: PlacementRule,
+    pub mine: PlacementRule,
+    pub target: PlacementRule,
+    pub rocket_launcher: PlacementRule,
+}
+
+impl Default for PlacementRules {
+    fn default() -> Self {
+        PlacementRules {
+            spawn: PlacementRule {
+                min_spacing: 2.0,
+                ..Default::default()
+            },
+            mine: PlacementRule {
+                min_spawn_distance: 4,
+                min_spacing: 2.0,
+                ..Default::default()
+            },
+            target: PlacementRule {
+                min_spawn_distance: 6,
+                ..Default::default()
+            },
+            rocket_launcher: PlacementRule {
+                min_spawn_distance: 4,
+                min_spacing: 2.0,
+                corridor_line_of_sight: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl PlacementRules {
+    pub fn get(&self, kind: ::level::EntityKind) -> &PlacementRule {
+        match kind {
+            ::level::EntityKind::Mine => &self.mine,
+            ::level::EntityKind::Target => &self.target,
+            ::level::EntityKind::RocketLauncher => &self.rocket_launcher,
+        }
+    }
+}
+
+/// Rule no tried cell satisfied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constraint {
+    NoFreeCell,
+    SpawnDistance,
+    Zone,
+    Spacing,
+    LineOfSight,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Constraint::*;
+        let text = match *self {
+            NoFreeCell => "no free cell",
+            SpawnDistance => "minimum distance from spawns",
+            Zone => "zone",
+            Spacing => "minimum spacing",
+            LineOfSight => "line of sight to a corridor",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Cells of the zones found in the graph of the maze
+pub struct GraphZones {
+    graph: ::graph::MazeGraph,
+    dead_ends: HashSet<::na::Vector3<isize>>,
+    farthest_room: HashSet<::na::Vector3<isize>>,
+    critical_path: HashSet<::na::Vector3<isize>>,
+}
+
+impl GraphZones {
+    /// Zones depending on spawns are empty until they are set
+    pub fn new(maze: &::maze::Maze<::na::U3>) -> Self {
+        let graph = ::graph::MazeGraph::new(maze);
+        let dead_ends = graph
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|&(_, node)| node.kind == ::graph::NodeKind::DeadEnd)
+            .map(|(id, _)| id)
+            .chain(graph.dead_end_rooms())
+            .flat_map(|id| graph.nodes[id].cells.iter().cloned())
+            .collect();
+        GraphZones {
+            graph,
+            dead_ends,
+            farthest_room: HashSet::new(),
+            critical_path: HashSet::new(),
+        }
+    }
+
+    /// The graph must have been computed before spawns are inserted in the maze walls
+    pub fn set_spawns(&mut self, spawns: &[::na::Vector3<isize>]) {
+        self.farthest_room.clear();
+        self.critical_path.clear();
+        let from = match spawns.first().and_then(|spawn| self.graph.node_near(spawn)) {
+            Some(from) => from,
+            None => return,
+        };
+        let to = match self.graph.farthest_room_from(from) {
+            Some(to) => to,
+            None => return,
+        };
+        self.farthest_room = self.graph.nodes[to].cells.clone();
+        for node in self.graph.critical_path(from, to).unwrap_or_default() {
+            if self.graph.nodes[node].kind == ::graph::NodeKind::Room {
+                self.critical_path.extend(self.graph.nodes[node].cells.iter().cloned());
+            }
+        }
+    }
+
+    /// None for zones not found in the graph
+    fn cells(&self, zone: Zone) -> Option<&HashSet<::na::Vector3<isize>>> {
+        match zone {
+            Zone::DeadEnd => Some(&self.dead_ends),
+            Zone::FarthestRoom => Some(&self.farthest_room),
+            Zone::CriticalPath => Some(&self.critical_path),
+            Zone::Any | Zone::Room | Zone::Corridor => None,
+        }
+    }
+}
+
+/// Number of moves from the closest spawn to every cell
+pub struct SpawnDistances {
+    grid: ::grid::Grid<::na::U3>,
+    /// The maze is extended so paths can go around it
+    offset: ::na::Vector3<isize>,
+    fields: Vec<Vec<Option<isize>>>,
+}
+
+impl SpawnDistances {
+    pub fn new(maze: &::maze::Maze<::na::U3>, spawns: &[::na::Vector3<isize>]) -> Self {
+        let mut maze = maze.clone();
+        let offset = maze.extend(1);
+        SpawnDistances {
+            grid: ::grid::Grid::new(maze.size()),
+            fields: spawns
+                .iter()
+                .map(|spawn| maze.distances_from(&(spawn + offset)))
+                .collect(),
+            offset,
+        }
+    }
+
+    /// None if no spawn can reach the cell
+    pub fn get(&self, cell: &::na::Vector3<isize>) -> Option<usize> {
+        let index = self.grid.index(&(cell + self.offset))? as usize;
+        // opening costs are 10 per move
+        self.fields
+            .iter()
+            .filter_map(|field| field[index])
+            .min()
+            .map(|d| d as usize / 10)
+    }
+}
+
+/// Place an entity on a random cell satisfying the rule, the cell is inserted in the maze walls
+///
+/// `others` are the cells the spacing is computed from
+pub fn place<R: Rng>(
+    maze: &mut ::maze::Maze<::na::U3>,
+    rule: &PlacementRule,
+    spawn_distances: Option<&SpawnDistances>,
+    zones: &GraphZones,
+    others: &[::na::Vector3<isize>],
+    rng: &mut R,
+) -> Result<::na::Vector3<isize>, Constraint> {
+    let mut free_cells = maze.free_cells();
+    if free_cells.is_empty() {
+        return Err(Constraint::NoFreeCell);
+    }
+    // graph zones can be a few cells a random cell of the maze would hardly hit
+    if let Some(cells) = zones.cells(rule.zone) {
+        free_cells.retain(|cell| cells.contains(cell));
+        if free_cells.is_empty() {
+            return Err(Constraint::Zone);
+        }
+    }
+
+    let mut failures = vec![];
+    for _ in 0..TRIES {
+        let cell = *rng.choose(&free_cells).unwrap();
+        match check(maze, rule, spawn_distances, zones, others, &cell) {
+            Ok(()) => {
+                maze.walls.insert(&cell);
+                return Ok(cell);
+            }
+            Err(constraint) => failures.push(constraint),
+        }
+    }
+
+    // the constraint rejecting the most cells
+    let mut counts = failures
+        .iter()
+        .map(|c| (failures.iter().filter(|f| *f == c).count(), *c))
+        .collect::<Vec<_>>();
+    counts.sort_by_key(|&(count, _)| count);
+    Err(counts.last().map_or(Constraint::NoFreeCell, |&(_, c)| c))
+}
+
+fn check(
+    maze: &::maze::Maze<::na::U3>,
+    rule: &PlacementRule,
+    spawn_distances: Option<&SpawnDistances>,
+    zones: &GraphZones,
+    others: &[::na::Vector3<isize>],
+    cell: &::na::Vector3<isize>,
+) -> Result<(), Constraint> {
+    if let Some(spawn_distances) = spawn_distances {
+        match spawn_distances.get(cell) {
+            Some(d) if d >= rule.min_spawn_distance => (),
+            _ => return Err(Constraint::SpawnDistance),
+        }
+    }
+
+    let zone_ok = match rule.zone {
+        Zone::Any => true,
+        Zone::Room => !maze.is_corridor(cell),
+        Zone::Corridor => maze.is_corridor(cell),
+        zone => zones.cells(zone).map_or(false, |cells| cells.contains(cell)),
+    };
+    if !zone_ok {
+        return Err(Constraint::Zone);
+    }
+
+    let too_close = others
+        .iter()
+        .any(|other| (other - cell).map(|c| c as f32).norm() < rule.min_spacing);
+    if too_close {
+        return Err(Constraint::Spacing);
+    }
+
+    if rule.corridor_line_of_sight && !sees_corridor(maze, cell) {
+        return Err(Constraint::LineOfSight);
+    }
+    Ok(())
+}
+
+/// Whereas a corridor is visible from the cell along an axis
+fn sees_corridor(maze: &::maze::Maze<::na::U3>, cell: &::na::Vector3<isize>) -> bool {
+    for direction in &maze.neighbours {
+        let mut current = *cell;
+        for _ in 0..SIGHT {
+            current = maze.neighbour(&current, direction);
+            if !maze.is_inside(&current) || maze.walls.contains(&current) {
+                break;
+            }
+            if maze.is_corridor(&current) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[test]
+fn rules_are_reported() {
+    let mut maze = ::maze::Maze::new_rectangle(::na::Vector3::new(5, 5, 5));
+    let zones = GraphZones::new(&maze);
+    let mut rng = ::util::seeded_rng(0);
+    let rule = PlacementRule {
+        min_spacing: 100.0,
+        ..Default::default()
+    };
+    let first = place(&mut maze, &rule, None, &zones, &[], &mut rng).unwrap();
+    assert!(maze.walls.contains(&first));
+    assert_eq!(place(&mut maze, &rule, None, &zones, &[first], &mut rng), Err(Constraint::Spacing));
+
+    let distances = SpawnDistances::new(&maze, &[first]);
+    let rule = PlacementRule {
+        min_spawn_distance: 100,
+        ..Default::default()
+    };
+    assert_eq!(
+        place(&mut maze, &rule, Some(&distances), &zones, &[], &mut rng),
+        Err(Constraint::SpawnDistance)
+    );
+}
+
+#[test]
+fn full_maze_has_no_free_cell() {
+    let mut maze = ::maze::Maze::new_rectangle(::na::Vector3::new(1, 1, 1));
+    let zones = GraphZones::new(&maze);
+    let mut rng = ::util::seeded_rng(0);
+    let rule = PlacementRule::default();
+    place(&mut maze, &rule, None, &zones, &[], &mut rng).unwrap();
+    assert_eq!(place(&mut maze, &rule, None, &zones, &[], &mut rng), Err(Constraint::NoFreeCell));
+}
+
+#[test]
+fn graph_zones() {
+    // rooms a and b joined by a corridor, a dead end leaves b
+    let free = [
+        (1, 1), (2, 1), (1, 2), (2, 2), (1, 3), (2, 3),
+        (3, 2), (4, 2),
+        (5, 1), (6, 1), (7, 1), (5, 2), (6, 2), (7, 2), (5, 3), (6, 3), (7, 3),
+        (6, 4), (6, 5),
+    ];
+    let mut maze = ::maze::walled(::na::Vector3::new(9, 7, 3), |c| c[2] == 1 && free.contains(&(c[0], c[1])));
+    let mut zones = GraphZones::new(&maze);
+    let mut rng = ::util::seeded_rng(0);
+    let rule = |zone| PlacementRule {
+        zone,
+        ..Default::default()
+    };
+    assert_eq!(
+        place(&mut maze, &rule(Zone::FarthestRoom), None, &zones, &[], &mut rng),
+        Err(Constraint::Zone)
+    );
+
+    let spawn = ::na::Vector3::new(1, 1, 1);
+    maze.walls.insert(&spawn);
+    zones.set_spawns(&[spawn]);
+    for _ in 0..3 {
+        let target = place(&mut maze, &rule(Zone::FarthestRoom), None, &zones, &[], &mut rng)
+            .unwrap();
+        assert!(target[0] >= 5);
+        let dead_end = place(&mut maze, &rule(Zone::DeadEnd), None, &zones, &[], &mut rng).unwrap();
+        assert!(dead_end[0] <= 2 || dead_end == ::na::Vector3::new(6, 5, 1));
+    }
+    let ambush = place(&mut maze, &rule(Zone::CriticalPath), None, &zones, &[], &mut rng).unwrap();
+    assert!(ambush[0] <= 2 || (ambush[0] >= 5 && ambush[1] <= 3));
+}
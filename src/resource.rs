@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::fmt;
 use std::io::Write;
@@ -9,6 +10,107 @@ use vulkano::pipeline::viewport::Viewport;
 pub type PhysicWorld = ::nphysics::world::World<f32>;
 #[derive(Deref, DerefMut)]
 pub struct PlayersEntities(pub [Option<::specs::Entity>; 3]);
+#[derive(Deref, DerefMut)]
+pub struct PlayersLives(pub [PlayerLife; 3]);
+
+impl PlayersLives {
+    /// Players are created on the next update
+    pub fn new(lives: usize) -> Self {
+        PlayersLives([PlayerLife::new(lives); 3])
+    }
+
+    /// All players are dead and have no life left
+    pub fn game_over(&self, number_of_player: usize, players_entities: &PlayersEntities) -> bool {
+        (0..number_of_player).all(|player| self.out_of_lives(player, players_entities))
+    }
+
+    /// The player is dead and won't be created again
+    pub fn out_of_lives(&self, player: usize, players_entities: &PlayersEntities) -> bool {
+        players_entities[player].is_none() && self[player].respawn_timer.is_none()
+            && self[player].lives == 0
+    }
+
+    /// Each player among the entities restarts from its given position
+    pub fn set_checkpoints(
+        &mut self,
+        positions: &[(::specs::Entity, ::na::Vector3<f32>)],
+        players_entities: &PlayersEntities,
+    ) {
+        for &(entity, position) in positions {
+            if let Some(player) = players_entities.iter().position(|&e| e == Some(entity)) {
+                self[player].checkpoint = Some(position);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerLife {
+    /// Number of times the player can still be created
+    pub lives: usize,
+    /// Time before the player is created, none if alive or out of lives
+    pub respawn_timer: Option<f32>,
+    /// Last position where the player collected a target
+    pub checkpoint: Option<::na::Vector3<f32>>,
+}
+
+impl PlayerLife {
+    pub fn new(lives: usize) -> Self {
+        PlayerLife {
+            lives,
+            respawn_timer: Some(0.0),
+            checkpoint: None,
+        }
+    }
+}
+
+/// Level loaded in the world, used to restart it
+#[derive(Deref, DerefMut)]
+pub struct CurrentLevel(pub ::level::LevelDescription);
+
+#[derive(Deref, DerefMut)]
+pub struct PlayersTolerances(pub [Tolerance; 3]);
+
+/// Damages are divided by the tolerance to their hazard, it handicaps players of different skills
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub wall: f32,
+    pub rocket: f32,
+    pub mine: f32,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            wall: 1.0,
+            rocket: 1.0,
+            mine: 1.0,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn get(&self, hazard: ::component::Hazard) -> f32 {
+        match hazard {
+            ::component::Hazard::Wall => self.wall,
+            ::component::Hazard::Rocket => self.rocket,
+            ::component::Hazard::Mine => self.mine,
+        }
+    }
+
+    /// Change the tolerance by steps of the configuration, within its bounds
+    pub fn shift(&mut self, hazard: ::component::Hazard, steps: f32) {
+        let tolerance = match hazard {
+            ::component::Hazard::Wall => &mut self.wall,
+            ::component::Hazard::Rocket => &mut self.rocket,
+            ::component::Hazard::Mine => &mut self.mine,
+        };
+        *tolerance = (*tolerance + steps * ::CFG.tolerance_step)
+            .max(::CFG.tolerance_step)
+            .min(::CFG.tolerance_max);
+    }
+}
+
 #[derive(Deref, DerefMut)]
 pub struct PlayersControllers(pub [Option<Controller>; 3]);
 
@@ -205,6 +307,114 @@ pub struct Tiles(pub Vec<::tile::Tile>);
 #[derive(Deref, DerefMut)]
 pub struct Tubes(pub Vec<::tube::Tube>);
 
+/// Tubes riders can travel through
+pub struct Rails {
+    pub rails: Vec<::tube::Rail>,
+    /// Distance travelled per second
+    pub speed: f32,
+    /// Velocity given when leaving a rail
+    pub exit_impulse: f32,
+    /// Distance from a rail end under which a rider enters it
+    pub entry_distance: f32,
+}
+
+impl Rails {
+    /// Rails along paths returned by `tube::generate_paths`
+    pub fn new(paths: &[Vec<::na::Vector3<isize>>], unit: f32) -> Self {
+        Rails {
+            rails: paths.iter().map(|path| ::tube::Rail::new(path, unit)).collect(),
+            speed: ::CFG.rail_speed * unit,
+            exit_impulse: ::CFG.rail_exit_impulse * unit,
+            entry_distance: ::CFG.rail_entry_distance * unit,
+        }
+    }
+}
+
+/// World position where each player is created
+#[derive(Deref, DerefMut)]
+pub struct Spawns(pub Vec<::na::Vector3<f32>>);
+
+/// Distance fields to players over the maze, used by homing entities
+pub struct Navigation {
+    maze: ::maze::Maze<::na::U3>,
+    /// Added to grid positions: the maze is extended so entities can go around it
+    offset: ::na::Vector3<isize>,
+    unit: f32,
+    grid: ::grid::Grid<::na::U3>,
+    /// Cell of each player and the distances to it
+    fields: HashMap<::specs::Entity, (::na::Vector3<isize>, Vec<Option<isize>>)>,
+}
+
+impl Navigation {
+    pub fn new(mut maze: ::maze::Maze<::na::U3>, unit: f32) -> Self {
+        let offset = maze.extend(1);
+        Navigation {
+            grid: ::grid::Grid::new(maze.size()),
+            maze,
+            offset,
+            unit,
+            fields: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, position: &::na::Vector3<f32>) -> ::na::Vector3<isize> {
+        ::util::to_grid(position, self.unit) + self.offset
+    }
+
+    fn distance(&self, field: &[Option<isize>], cell: &::na::Vector3<isize>) -> Option<isize> {
+        self.grid.index(cell).and_then(|index| field[index as usize])
+    }
+
+    /// Compute the field of the player only if it changed of cell
+    pub fn update(&mut self, player: ::specs::Entity, position: &::na::Vector3<f32>) {
+        let cell = self.cell(position);
+        if self.fields.get(&player).map_or(false, |&(ref c, _)| *c == cell) {
+            return;
+        }
+        let field = self.maze.distances_from(&cell);
+        self.fields.insert(player, (cell, field));
+    }
+
+    pub fn retain<F: FnMut(::specs::Entity) -> bool>(&mut self, mut f: F) {
+        self.fields.retain(|&entity, _| f(entity));
+    }
+
+    /// Path distance to the player and the direction to follow
+    ///
+    /// None if the player can't be reached from the position
+    pub fn toward(
+        &self,
+        player: ::specs::Entity,
+        player_position: &::na::Vector3<f32>,
+        position: &::na::Vector3<f32>,
+    ) -> Option<(f32, ::na::Vector3<f32>)> {
+        let &(ref player_cell, ref field) = self.fields.get(&player)?;
+        let cell = self.cell(position);
+        if cell == *player_cell {
+            let vector = player_position - position;
+            return Some((vector.norm(), vector));
+        }
+
+        // opening costs are 10 per unit
+        let distance = self.distance(field, &cell)? as f32 / 10.0 * self.unit;
+        let next = self.maze
+            .free_successors(&cell)
+            .into_iter()
+            .filter_map(|(next, _)| self.distance(field, &next).map(|d| (next, d)))
+            .min_by_key(|&(_, d)| d)
+            .map(|(next, _)| next)?;
+        let direction = ::util::to_world(&(next - self.offset), self.unit) - position;
+        Some((distance, direction))
+    }
+}
+
+/// Bodies leaving the level on a wrapped axis enter it on the opposite face
+pub struct Wrap {
+    pub axes: [bool; 3],
+    /// The level goes from the origin to size
+    pub size: ::na::Vector3<f32>,
+}
+
 const APP_INFO: AppInfo = AppInfo {
     name: "SESE",
     author: "thiolliere",
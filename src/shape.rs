@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Outer shape of a level, fitted in the maze box
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Cuboid,
+    Sphere,
+    /// Along the z axis
+    Cylinder,
+    /// Around the z axis
+    Torus,
+    /// Heights between 0.0 and 1.0 of the columns along z, row major
+    Heightmap {
+        width: usize,
+        height: usize,
+        heights: Vec<f32>,
+    },
+    /// Explicit maze cells
+    Cells(Vec<[isize; 3]>),
+}
+
+impl Shape {
+    /// Grayscale image, white is the top of the maze
+    pub fn heightmap_from_file<P: AsRef<Path>>(path: P) -> Result<Self, ::failure::Error> {
+        let image = ::image::open(path)?.to_luma();
+        let heights = image.pixels().map(|p| p.data[0] as f32 / 255.0).collect();
+        Ok(Shape::Heightmap {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            heights,
+        })
+    }
+
+    /// Heightmaps need a height for each of their columns
+    pub fn check(&self) -> Result<(), ::level::ValidationError> {
+        match *self {
+            Shape::Heightmap {
+                width,
+                height,
+                ref heights,
+            } if width == 0 || height == 0 || heights.len() != width * height =>
+            {
+                Err(::level::ValidationError::HeightmapSize {
+                    width,
+                    height,
+                    heights: heights.len(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Cells inside the shape, none for a cuboid
+    pub fn mask(&self, size: ::na::Vector3<isize>) -> Option<HashSet<::na::Vector3<isize>>> {
+        match *self {
+            Shape::Cuboid => return None,
+            Shape::Cells(ref cells) => {
+                return Some(
+                    cells
+                        .iter()
+                        .map(|c| ::na::Vector3::new(c[0], c[1], c[2]))
+                        .filter(|c| c >= &::na::zero() && c < &size)
+                        .collect(),
+                )
+            }
+            _ => (),
+        }
+
+        let mut mask = HashSet::new();
+        for x in 0..size[0] {
+            for y in 0..size[1] {
+                for z in 0..size[2] {
+                    let cell = ::na::Vector3::new(x, y, z);
+                    if self.contains(size, &cell) {
+                        mask.insert(cell);
+                    }
+                }
+            }
+        }
+        Some(mask)
+    }
+
+    pub fn contains(&self, size: ::na::Vector3<isize>, cell: &::na::Vector3<isize>) -> bool {
+        // position in [-1, 1] on each axis
+        let n = ::na::Vector3::new(
+            2.0 * (cell[0] as f32 + 0.5) / size[0] as f32 - 1.0,
+            2.0 * (cell[1] as f32 + 0.5) / size[1] as f32 - 1.0,
+            2.0 * (cell[2] as f32 + 0.5) / size[2] as f32 - 1.0,
+        );
+
+        match *self {
+            Shape::Cuboid => n.iter().all(|c| c.abs() <= 1.0),
+            Shape::Sphere => n.norm_squared() <= 1.0,
+            Shape::Cylinder => n[0].powi(2) + n[1].powi(2) <= 1.0,
+            Shape::Torus => {
+                const MAJOR: f32 = 0.65;
+                const MINOR: f32 = 0.35;
+                let radial = (n[0].powi(2) + n[1].powi(2)).sqrt() - MAJOR;
+                (radial / MINOR).powi(2) + n[2].powi(2) <= 1.0
+            }
+            Shape::Heightmap {
+                width,
+                height,
+                ref heights,
+            } => {
+                let x = cell[0] as usize * width / size[0] as usize;
+                let y = cell[1] as usize * height / size[1] as usize;
+                let top = (heights[y * width + x] * size[2] as f32).ceil().max(1.0);
+                (cell[2] as f32) < top
+            }
+            Shape::Cells(ref cells) => cells.contains(&[cell[0], cell[1], cell[2]]),
+        }
+    }
+}
+
+#[test]
+fn sphere_mask_is_inscribed() {
+    let size = ::na::Vector3::new(9, 9, 9);
+    let mask = Shape::Sphere.mask(size).unwrap();
+    assert!(mask.contains(&::na::Vector3::new(4, 4, 4)));
+    assert!(mask.contains(&::na::Vector3::new(0, 4, 4)));
+    assert!(!mask.contains(&::na::Vector3::new(0, 0, 0)));
+    assert!(Shape::Cuboid.mask(size).is_none());
+}
@@ -0,0 +1,69 @@
+use rand::distributions::{Distribution, UnitSphereSurface};
+use rand::thread_rng;
+use specs::Join;
+
+/// Apply the force and damping of the behaviour of monsters
+pub struct BehaviourSystem;
+
+impl<'a> ::specs::System<'a> for BehaviourSystem {
+    type SystemData = (
+        ::specs::WriteStorage<'a, ::component::Behaviour>,
+        ::specs::ReadStorage<'a, ::component::ClosestPlayer>,
+        ::specs::ReadStorage<'a, ::component::Visibility>,
+        ::specs::WriteStorage<'a, ::component::PhysicBody>,
+        ::specs::ReadExpect<'a, ::resource::UpdateTime>,
+        ::specs::WriteExpect<'a, ::resource::PhysicWorld>,
+        ::specs::Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut behaviours,
+            closest_players,
+            visibilities,
+            mut bodies,
+            update_time,
+            mut physic_world,
+            entities,
+        ): Self::SystemData,
+    ) {
+        for (behaviour, closest_player, body, entity) in
+            (&mut behaviours, &closest_players, &mut bodies, &*entities).join()
+        {
+            let body = body.get_mut(&mut physic_world);
+            body.clear_forces();
+
+            let closest_player = closest_player
+                .vector
+                .map(|vector| (vector, closest_player.distance));
+            let distance = closest_player.map(|(_, distance)| distance);
+            let last_seen = visibilities.get(entity).and_then(|v| v.last_seen);
+
+            let motion = match behaviour.behaviour.motion(&mut behaviour.latched, distance, last_seen) {
+                Some(motion) => motion,
+                None => continue,
+            };
+
+            behaviour.wander_timer -= update_time.0;
+            if behaviour.wander_timer <= 0.0 {
+                let direction = UnitSphereSurface::new().sample(&mut thread_rng());
+                behaviour.wander_direction =
+                    ::na::Vector3::new(direction[0] as f32, direction[1] as f32, direction[2] as f32);
+                behaviour.wander_timer = motion.period;
+            }
+
+            let mut lin_vel = body.lin_vel() * motion.damping;
+            if motion.mode == ::behaviour::Mode::Bouncing {
+                lin_vel = if lin_vel.norm() > 1e-6 {
+                    lin_vel.normalize() * motion.force
+                } else {
+                    behaviour.wander_direction * motion.force
+                };
+            }
+            body.set_lin_vel_internal(lin_vel);
+
+            body.append_lin_force(motion.force(closest_player, lin_vel, behaviour.wander_direction));
+        }
+    }
+}
@@ -8,6 +8,8 @@ impl<'a> ::specs::System<'a> for ClosestPlayerSystem {
         ::specs::ReadStorage<'a, ::component::PhysicBody>,
         ::specs::WriteStorage<'a, ::component::ClosestPlayer>,
         ::specs::ReadExpect<'a, ::resource::PhysicWorld>,
+        ::specs::WriteExpect<'a, ::resource::Navigation>,
+        ::specs::Entities<'a>,
     );
 
     fn run(
@@ -17,15 +19,31 @@ impl<'a> ::specs::System<'a> for ClosestPlayerSystem {
             bodies,
             mut closest_players,
             physic_world,
+            mut navigation,
+            entities,
         ): Self::SystemData,
     ) {
+        navigation.retain(|entity| entities.is_alive(entity) && players.get(entity).is_some());
+        for (_, body, entity) in (&players, &bodies, &*entities).join() {
+            let position = body.get(&physic_world).position().translation.vector;
+            navigation.update(entity, &position);
+        }
+
         for (closest_player, body) in (&mut closest_players, &bodies).join() {
             let position = body.get(&physic_world).position().translation.vector;
-            closest_player.vector = (&players, &bodies).join()
-                .map(|(_, player_body)| {
-                    player_body.get(&physic_world).position().translation.vector - position
+            let closest = (&players, &bodies, &*entities).join()
+                .map(|(_, player_body, player)| {
+                    let player_position = player_body.get(&physic_world).position().translation.vector;
+                    navigation.toward(player, &player_position, &position)
+                        .unwrap_or_else(|| {
+                            let vector = player_position - position;
+                            (vector.norm(), vector)
+                        })
                 })
-                .min_by_key(|v| (v.norm()*10000.0) as usize);
+                .min_by_key(|&(distance, _)| (distance*10000.0) as usize);
+
+            closest_player.vector = closest.map(|(_, vector)| vector);
+            closest_player.distance = closest.map_or(0.0, |(distance, _)| distance);
         }
     }
 }
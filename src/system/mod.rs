@@ -1,6 +1,10 @@
+pub mod behaviour;
 pub mod physic;
+pub mod wrap;
+pub mod rail;
 pub mod target;
 pub mod player_killer;
 pub mod rocket_launcher;
 pub mod closest_player;
+pub mod visibility;
 pub mod player_creator;
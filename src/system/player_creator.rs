@@ -1,41 +1,183 @@
+use specs::Join;
+
+/// Create players after their respawn delay while they have lives left
 pub struct PlayerCreatorSystem;
 
-// TODO: avec un temps avant de le respawn
 impl<'a> ::specs::System<'a> for PlayerCreatorSystem {
     type SystemData = (
+        ::specs::ReadStorage<'a, ::component::PlayerKiller>,
+        ::specs::ReadStorage<'a, ::component::PhysicBody>,
         ::specs::ReadExpect<'a, ::resource::Mode>,
-        ::specs::ReadExpect<'a, ::resource::PlayersEntities>,
-        ::specs::ReadExpect<'a, ::resource::PlayersControllers>,
+        ::specs::WriteExpect<'a, ::resource::PlayersEntities>,
+        ::specs::WriteExpect<'a, ::resource::PlayersLives>,
+        ::specs::ReadExpect<'a, ::resource::Spawns>,
+        ::specs::ReadExpect<'a, ::resource::UpdateTime>,
+        ::specs::ReadExpect<'a, ::resource::PhysicWorld>,
         ::specs::ReadExpect<'a, ::specs::LazyUpdate>,
-        ::specs::ReadExpect<'a, ::specs::world::EntitiesRes>,
+        ::specs::Entities<'a>,
     );
 
     fn run(
         &mut self,
         (
+            player_killers,
+            bodies,
             mode,
-            players_entities,
-            players_controllers,
+            mut players_entities,
+            mut players_lives,
+            spawns,
+            update_time,
+            physic_world,
             lazy_update,
             entities,
         ): Self::SystemData,
     ) {
+        // players out of the mode don't play
         for player in mode.number_of_player()..3 {
-            assert!(players_entities[player].is_none());
-            assert!(players_controllers[player].is_none());
+            if let Some(entity) = players_entities[player].take() {
+                entities.delete(entity).unwrap();
+            }
+        }
+
+        let mut dangers = (&player_killers, &bodies)
+            .join()
+            .map(|(_, body)| body.get(&physic_world).position().translation.vector)
+            .collect::<Vec<_>>();
+        for entity in players_entities.iter().filter_map(|&entity| entity) {
+            if let Some(body) = bodies.get(entity) {
+                dangers.push(body.get(&physic_world).position().translation.vector);
+            }
         }
 
         for player in 0..mode.number_of_player() {
-            if players_entities[player].map_or(true, |entity| !entities.is_alive(entity)) {
+            let life = &mut players_lives[player];
+
+            if let Some(entity) = players_entities[player] {
+                if entities.is_alive(entity) {
+                    continue;
+                }
+                players_entities[player] = None;
+                if life.lives > 0 {
+                    life.respawn_timer = Some(::CFG.respawn_delay);
+                }
+            }
+
+            if let Some(timer) = life.respawn_timer {
+                let timer = timer - update_time.0;
+                if timer > 0.0 {
+                    life.respawn_timer = Some(timer);
+                    continue;
+                }
+                life.respawn_timer = None;
+                if life.lives == 0 {
+                    continue;
+                }
+                life.lives -= 1;
+
+                let spawn = match life.checkpoint {
+                    Some(checkpoint) => checkpoint,
+                    None => safe_spawn(&spawns, &dangers).expect("level has no spawn"),
+                };
+                // players created on this update are dangers for the next ones
+                dangers.push(spawn);
                 lazy_update.exec(move |world| {
-                    let player_pos = ::na::Vector3::new(
-                        -10,
-                        -10+player as isize *2,
-                        -10,
-                    );
-                    ::entity::create_player(::util::to_world(&player_pos, 1.0), world);
+                    ::entity::create_player(player, spawn, world);
                 });
             }
         }
     }
 }
+
+/// The spawn the farthest from all dangers, the first one if there is no danger
+pub fn safe_spawn(
+    spawns: &[::na::Vector3<f32>],
+    dangers: &[::na::Vector3<f32>],
+) -> Option<::na::Vector3<f32>> {
+    spawns
+        .iter()
+        .map(|spawn| {
+            let distance = dangers
+                .iter()
+                .map(|danger| (danger - spawn).norm())
+                .fold(::std::f32::INFINITY, f32::min);
+            (spawn, distance)
+        })
+        .fold(None, |best: Option<(&::na::Vector3<f32>, f32)>, (spawn, distance)| match best {
+            Some((_, best_distance)) if best_distance >= distance => best,
+            _ => Some((spawn, distance)),
+        })
+        .map(|(spawn, _)| *spawn)
+}
+
+#[test]
+fn respawn_after_delay_until_out_of_lives() {
+    use specs::RunNow;
+
+    let mut world = ::specs::World::new();
+    world.register::<::component::Player>();
+    world.register::<::component::PlayerKiller>();
+    world.register::<::component::PhysicBody>();
+    world.register::<::component::Health>();
+    world.register::<::component::Contactor>();
+    world.register::<::component::Rider>();
+    world.register::<::component::FlightControl>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::UpdateTime(1.0));
+    world.add_resource(::resource::Mode::Mode2Player);
+    world.add_resource(::resource::PlayersEntities([None; 3]));
+    world.add_resource(::resource::PlayersLives::new(2));
+    let spawns = vec![::na::Vector3::new(0.0, 0.0, 0.0), ::na::Vector3::new(4.0, 0.0, 0.0)];
+    world.add_resource(::resource::Spawns(spawns.clone()));
+
+    let update = |world: &mut ::specs::World| {
+        PlayerCreatorSystem.run_now(&world.res);
+        world.maintain();
+    };
+    let position = |world: &::specs::World, player: usize| {
+        let entity = world.read_resource::<::resource::PlayersEntities>()[player]?;
+        let bodies = world.read_storage::<::component::PhysicBody>();
+        let physic_world = world.read_resource::<::resource::PhysicWorld>();
+        Some(bodies.get(entity)?.get(&physic_world).position().translation.vector)
+    };
+
+    // players are created at once on different spawns
+    update(&mut world);
+    let first = position(&world, 0).unwrap();
+    let second = position(&world, 1).unwrap();
+    assert!(spawns.contains(&first) && spawns.contains(&second) && first != second);
+
+    // the countdown starts on the update the death is noticed
+    let delay = ::CFG.respawn_delay.ceil() as usize;
+    for life in 0..2 {
+        let entity = world.read_resource::<::resource::PlayersEntities>()[0].unwrap();
+        world.delete_entity(entity).unwrap();
+        for _ in 1..delay {
+            update(&mut world);
+            assert!(position(&world, 0).is_none());
+        }
+        update(&mut world);
+        if life == 0 {
+            assert!(position(&world, 0).is_some());
+        }
+    }
+
+    // the second player is still alive
+    assert!(position(&world, 0).is_none());
+    let lives = world.read_resource::<::resource::PlayersLives>();
+    let players_entities = world.read_resource::<::resource::PlayersEntities>();
+    assert_eq!(lives[0].lives, 0);
+    assert!(!lives.game_over(2, &players_entities));
+    assert!(lives.game_over(1, &players_entities));
+}
+
+#[test]
+fn safe_spawn_avoids_dangers() {
+    let spawns = vec![
+        ::na::Vector3::new(0.0, 0.0, 0.0),
+        ::na::Vector3::new(5.0, 0.0, 0.0),
+        ::na::Vector3::new(2.0, 0.0, 0.0),
+    ];
+    assert_eq!(safe_spawn(&spawns, &[]), Some(spawns[0]));
+    assert_eq!(safe_spawn(&spawns, &[::na::Vector3::new(1.0, 0.0, 0.0)]), Some(spawns[1]));
+    assert_eq!(safe_spawn(&[], &[]), None);
+}
@@ -1,5 +1,9 @@
 use specs::Join;
 
+/// Damage players hit by killers or hitting walls, killers are destroyed on contact
+///
+/// Contacts with entities that are neither players nor killers are walls, riders are carried
+/// through tubes and aren't hurt by them
 pub struct PlayerKillerSystem;
 
 impl<'a> ::specs::System<'a> for PlayerKillerSystem {
@@ -7,6 +11,13 @@ impl<'a> ::specs::System<'a> for PlayerKillerSystem {
         ::specs::ReadStorage<'a, ::component::Player>,
         ::specs::ReadStorage<'a, ::component::Contactor>,
         ::specs::ReadStorage<'a, ::component::PlayerKiller>,
+        ::specs::ReadStorage<'a, ::component::PhysicBody>,
+        ::specs::ReadStorage<'a, ::component::Rider>,
+        ::specs::WriteStorage<'a, ::component::Health>,
+        ::specs::ReadExpect<'a, ::resource::PlayersEntities>,
+        ::specs::ReadExpect<'a, ::resource::PlayersTolerances>,
+        ::specs::ReadExpect<'a, ::resource::UpdateTime>,
+        ::specs::ReadExpect<'a, ::resource::PhysicWorld>,
         ::specs::Entities<'a>,
     );
 
@@ -16,20 +27,195 @@ impl<'a> ::specs::System<'a> for PlayerKillerSystem {
             players,
             contactors,
             player_killers,
+            bodies,
+            riders,
+            mut healths,
+            players_entities,
+            players_tolerances,
+            update_time,
+            physic_world,
             entities,
         ): Self::SystemData,
     ) {
-        for (_, contactor, entity) in (&player_killers, &contactors, &*entities).join() {
+        let mut damages = vec![];
+        for (player_killer, contactor, entity) in (&player_killers, &contactors, &*entities).join() {
             if !contactor.contacts.is_empty() {
                 for player in contactor.contacts.iter()
                     .map(|&(entity, _)| entity)
                     .filter(|&entity| players.get(entity).is_some())
-                    .collect::<Vec<_>>()
                 {
-                    entities.delete(player).unwrap();
+                    damages.push((player, player_killer.hazard, player_killer.damage));
                 }
                 entities.delete(entity).unwrap();
             }
         }
+
+        for (_, health, contactor, entity) in (&players, &healths, &contactors, &*entities).join() {
+            if riders.get(entity).map_or(false, |rider| rider.ride.is_some()) {
+                continue;
+            }
+            let speed = contactor.contacts.iter()
+                .filter(|&&(other, _)| players.get(other).is_none() && player_killers.get(other).is_none())
+                .map(|&(_, ref contact)| health.velocity.dot(&contact.normal).abs())
+                .fold(0.0, f32::max);
+            if speed > ::CFG.wall_damage_min_speed {
+                let damage = (speed - ::CFG.wall_damage_min_speed) * ::CFG.wall_damage_coef;
+                damages.push((entity, ::component::Hazard::Wall, damage));
+            }
+        }
+
+        for (health, body, entity) in (&mut healths, &bodies, &*entities).join() {
+            health.invulnerable = (health.invulnerable - update_time.0).max(0.0);
+            health.velocity = body.get(&physic_world).lin_vel();
+
+            let tolerance = players_entities.iter()
+                .position(|&player| player == Some(entity))
+                .map_or(::resource::Tolerance::default(), |player| players_tolerances[player]);
+
+            for &(_, hazard, damage) in damages.iter().filter(|&&(damaged, _, _)| damaged == entity) {
+                if health.invulnerable == 0.0 {
+                    health.health -= damage / tolerance.get(hazard);
+                    health.invulnerable = ::CFG.invulnerability_time;
+                }
+            }
+
+            if health.health <= 0.0 {
+                entities.delete(entity).unwrap();
+            }
+        }
+    }
+}
+
+#[test]
+fn tolerance_reduces_damages() {
+    use specs::{Builder, RunNow};
+
+    let mut world = ::specs::World::new();
+    world.register::<::component::Player>();
+    world.register::<::component::Contactor>();
+    world.register::<::component::PlayerKiller>();
+    world.register::<::component::PhysicBody>();
+    world.register::<::component::Health>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::UpdateTime(0.1));
+    world.add_resource(::resource::PlayersTolerances([::resource::Tolerance::default(); 3]));
+
+    let shape = ::ncollide::shape::Ball::new(0.1);
+    let body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    let player = world
+        .create_entity()
+        .with(::component::Player)
+        .with(::component::Contactor::new())
+        .with(::component::Health {
+            health: 1.0,
+            invulnerable: 0.0,
+            velocity: ::na::zero(),
+        })
+        .build();
+    ::component::PhysicBody::add(player, body, &mut world.write_storage(), &mut world.write_resource());
+    world.add_resource(::resource::PlayersEntities([Some(player), None, None]));
+    world.write_resource::<::resource::PlayersTolerances>()[0].shift(::component::Hazard::Mine, 1.0 / ::CFG.tolerance_step);
+
+    let contact = ::component::Contact::new(::na::Point3::origin(), ::na::Point3::origin(), ::na::Vector3::x(), 0.0);
+    let create_mine = |world: &mut ::specs::World| {
+        let mut contactor = ::component::Contactor::new();
+        contactor.contacts.push((player, contact.clone()));
+        world
+            .create_entity()
+            .with(::component::PlayerKiller {
+                hazard: ::component::Hazard::Mine,
+                damage: 0.8,
+            })
+            .with(contactor)
+            .build()
+    };
+
+    // tolerance 2 halves the damage
+    let mine = create_mine(&mut world);
+    PlayerKillerSystem.run_now(&world.res);
+    world.maintain();
+    assert!(!world.is_alive(mine));
+    assert!((world.read_storage::<::component::Health>().get(player).unwrap().health - 0.6).abs() < 1e-6);
+
+    // the second mine hits during the invulnerability
+    create_mine(&mut world);
+    PlayerKillerSystem.run_now(&world.res);
+    world.maintain();
+    assert!((world.read_storage::<::component::Health>().get(player).unwrap().health - 0.6).abs() < 1e-6);
+
+    for _ in 0..2 {
+        world.write_storage::<::component::Health>().get_mut(player).unwrap().invulnerable = 0.0;
+        create_mine(&mut world);
+        PlayerKillerSystem.run_now(&world.res);
+        world.maintain();
     }
+    assert!(!world.is_alive(player));
+}
+
+#[test]
+fn wall_impacts_damage_players() {
+    use specs::{Builder, RunNow};
+
+    let mut world = ::specs::World::new();
+    world.register::<::component::Player>();
+    world.register::<::component::Contactor>();
+    world.register::<::component::PlayerKiller>();
+    world.register::<::component::PhysicBody>();
+    world.register::<::component::Rider>();
+    world.register::<::component::Health>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::UpdateTime(0.1));
+    world.add_resource(::resource::PlayersTolerances([::resource::Tolerance::default(); 3]));
+
+    let wall = world.create_entity().build();
+    let contact = ::component::Contact::new(::na::Point3::origin(), ::na::Point3::origin(), ::na::Vector3::x(), 0.0);
+    let mut contactor = ::component::Contactor::new();
+    contactor.contacts.push((wall, contact));
+
+    let shape = ::ncollide::shape::Ball::new(0.1);
+    let body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    let player = world
+        .create_entity()
+        .with(::component::Player)
+        .with(contactor)
+        .with(::component::Rider::default())
+        .with(::component::Health {
+            health: 1.0,
+            invulnerable: 0.0,
+            velocity: ::na::zero(),
+        })
+        .build();
+    ::component::PhysicBody::add(player, body, &mut world.write_storage(), &mut world.write_resource());
+    world.add_resource(::resource::PlayersEntities([Some(player), None, None]));
+
+    // the velocity along the contact normal is what hurts
+    let hit = |world: &mut ::specs::World, velocity: ::na::Vector3<f32>| {
+        {
+            let mut healths = world.write_storage::<::component::Health>();
+            let health = healths.get_mut(player).unwrap();
+            health.invulnerable = 0.0;
+            health.velocity = velocity;
+        }
+        PlayerKillerSystem.run_now(&world.res);
+        world.maintain();
+        world.read_storage::<::component::Health>().get(player).unwrap().health
+    };
+
+    // below the threshold and sliding along the wall are harmless
+    let min_speed = ::CFG.wall_damage_min_speed;
+    assert_eq!(hit(&mut world, ::na::Vector3::x() * min_speed * 0.9), 1.0);
+    assert_eq!(hit(&mut world, ::na::Vector3::y() * min_speed * 2.0), 1.0);
+
+    // damage grows with the speed above the threshold
+    let health = hit(&mut world, -::na::Vector3::x() * (min_speed + 1.0));
+    assert!((health - (1.0 - ::CFG.wall_damage_coef)).abs() < 1e-6);
+
+    // riders go through tubes unharmed
+    world.write_storage::<::component::Rider>().get_mut(player).unwrap().ride = Some(::component::Ride {
+        rail: 0,
+        distance: 0.0,
+        reverse: false,
+    });
+    let ridden = hit(&mut world, ::na::Vector3::x() * (min_speed + 1.0));
+    assert_eq!(ridden, health);
 }
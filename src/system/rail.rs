@@ -0,0 +1,186 @@
+use specs::Join;
+
+/// Space left between the tube and a rider leaving it
+const EXIT_MARGIN: f32 = 0.05;
+
+/// Carry riders along rails, riders enter a rail near one of its ends and leave it at the other
+///
+/// Riders don't collide with tubes while riding and leave them sideways
+pub struct RailSystem;
+
+impl<'a> ::specs::System<'a> for RailSystem {
+    type SystemData = (
+        ::specs::WriteStorage<'a, ::component::Rider>,
+        ::specs::WriteStorage<'a, ::component::PhysicBody>,
+        ::specs::ReadExpect<'a, ::resource::Rails>,
+        ::specs::ReadExpect<'a, ::resource::UpdateTime>,
+        ::specs::WriteExpect<'a, ::resource::PhysicWorld>,
+    );
+
+    fn run(
+        &mut self,
+        (mut riders, mut bodies, rails, update_time, mut physic_world): Self::SystemData,
+    ) {
+        for (rider, body) in (&mut riders, &mut bodies).join() {
+            let body = body.get_mut(&mut physic_world);
+            let mut position = body.position().clone();
+
+            let ride = match rider.ride {
+                Some(ride) => ride,
+                None => {
+                    let pos = position.translation.vector;
+                    if let Some(end) = rider.left_end {
+                        if (end - pos).norm() > 2.0 * rails.entry_distance {
+                            rider.left_end = None;
+                        }
+                    }
+                    let left_end = rider.left_end;
+                    rider.ride = rails
+                        .rails
+                        .iter()
+                        .enumerate()
+                        .flat_map(|(id, rail)| vec![(id, rail.start(), false), (id, rail.end(), true)])
+                        .filter(|&(_, end, _)| left_end.map_or(true, |left| left != end))
+                        .find(|&(_, end, _)| (end - pos).norm() < rails.entry_distance)
+                        .map(|(rail, _, reverse)| ::component::Ride {
+                            rail,
+                            distance: 0.0,
+                            reverse,
+                        });
+                    if rider.ride.is_some() {
+                        set_tube_collisions(body, false);
+                    }
+                    continue;
+                }
+            };
+
+            let rail = &rails.rails[ride.rail];
+            let distance = ride.distance + rails.speed * update_time.0;
+            if distance >= rail.length() {
+                let (end, normal) = if ride.reverse {
+                    (rail.start(), rail.start_normal())
+                } else {
+                    (rail.end(), rail.end_normal())
+                };
+                let side = side(&normal);
+                let clearance = ::tube::RADIUS + ::CFG.ball_radius + EXIT_MARGIN;
+                position.translation.vector = end + normal * rails.entry_distance + side * clearance;
+                body.set_transformation(position);
+                body.set_lin_vel(side * rails.exit_impulse);
+                set_tube_collisions(body, true);
+                rider.ride = None;
+                rider.left_end = Some(end);
+            } else {
+                let (point, direction) = if ride.reverse {
+                    let (point, direction) = rail.point_at(rail.length() - distance);
+                    (point, -direction)
+                } else {
+                    rail.point_at(distance)
+                };
+                position.translation.vector = point;
+                body.set_transformation(position);
+                body.set_lin_vel(direction * rails.speed);
+                rider.ride = Some(::component::Ride { distance, ..ride });
+            }
+        }
+    }
+}
+
+fn set_tube_collisions(body: &mut ::nphysics::object::RigidBody<f32>, collide: bool) {
+    let mut groups = body.collision_groups().clone();
+    groups.modify_blacklist(::entity::Group::Tube as usize, !collide);
+    body.set_collision_groups(groups);
+}
+
+/// Unit vector orthogonal to the direction
+fn side(direction: &::na::Vector3<f32>) -> ::na::Vector3<f32> {
+    let axis = if direction.x.abs() < 0.5 {
+        ::na::Vector3::x()
+    } else {
+        ::na::Vector3::y()
+    };
+    direction.cross(&axis).normalize()
+}
+
+#[test]
+fn rider_follows_generated_path() {
+    use specs::{Builder, RunNow};
+
+    // two pillars the tube joins
+    let mut maze = ::maze::Maze::new_rectangle(::na::Vector3::new(9, 5, 5));
+    maze.walls.insert(&::na::Vector3::new(1, 2, 2));
+    maze.walls.insert(&::na::Vector3::new(7, 2, 2));
+    let paths = ::tube::generate_paths(0, &mut maze, &mut ::util::seeded_rng(0));
+    assert!(!paths.is_empty());
+
+    let rail = ::tube::Rail::new(&paths[0], 1.0);
+    let mut world = ::specs::World::new();
+    world.register::<::component::Rider>();
+    world.register::<::component::PhysicBody>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::UpdateTime(0.05));
+    world.add_resource(::resource::Rails {
+        rails: vec![rail.clone()],
+        speed: 2.0,
+        exit_impulse: 1.0,
+        entry_distance: 0.25,
+    });
+
+    let shape = ::ncollide::shape::Ball::new(0.1);
+    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    body.set_transformation(::na::Isometry3::new(rail.start(), ::na::zero()));
+    let entity = world.create_entity().with(::component::Rider::default()).build();
+    ::component::PhysicBody::add(entity, body, &mut world.write_storage(), &mut world.write_resource());
+
+    let position = |world: &::specs::World| {
+        let bodies = world.read_storage::<::component::PhysicBody>();
+        let physic_world = world.read_resource::<::resource::PhysicWorld>();
+        let body = bodies.get(entity).unwrap().get(&physic_world);
+        (body.position().translation.vector, body.lin_vel())
+    };
+    let riding = |world: &::specs::World| {
+        world.read_storage::<::component::Rider>().get(entity).unwrap().ride.is_some()
+    };
+
+    let mut tube_groups = ::nphysics::object::RigidBodyCollisionGroups::new_static();
+    tube_groups.set_membership(&[::entity::Group::Wall as usize, ::entity::Group::Tube as usize]);
+    let tube_groups = tube_groups.as_collision_groups().clone();
+    let body_groups = |world: &::specs::World| {
+        let bodies = world.read_storage::<::component::PhysicBody>();
+        let physic_world = world.read_resource::<::resource::PhysicWorld>();
+        bodies.get(entity).unwrap().get(&physic_world).collision_groups().clone()
+    };
+
+    RailSystem.run_now(&world.res);
+    assert!(riding(&world));
+    assert!(!tube_groups.can_interact_with(body_groups(&world).as_collision_groups()));
+
+    let mut steps = 0;
+    let mut last_distance = 0.0;
+    while riding(&world) {
+        RailSystem.run_now(&world.res);
+        steps += 1;
+        assert!(steps < 1000);
+        if riding(&world) {
+            // the rider is on the rail and moves forward
+            let ride = world.read_storage::<::component::Rider>().get(entity).unwrap().ride.unwrap();
+            assert!(ride.distance > last_distance);
+            last_distance = ride.distance;
+            assert!((position(&world).0 - rail.point_at(ride.distance).0).norm() < 1e-4);
+        }
+    }
+
+    // the rider leaves sideways, clear of the tube
+    let (pos, vel) = position(&world);
+    let normal = rail.end_normal();
+    let offset = pos - rail.end();
+    assert!((offset.dot(&normal) - 0.25).abs() < 1e-4);
+    assert!((offset - normal * offset.dot(&normal)).norm() > ::tube::RADIUS + 0.1);
+    assert!(vel.dot(&normal).abs() < 1e-4);
+    assert!((vel.norm() - 1.0).abs() < 1e-4);
+    assert!(tube_groups.can_interact_with(body_groups(&world).as_collision_groups()));
+
+    // leaving the rail doesn't enter it again
+    RailSystem.run_now(&world.res);
+    assert!(!riding(&world));
+}
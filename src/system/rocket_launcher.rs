@@ -5,6 +5,7 @@ pub struct RocketLauncherSystem;
 impl<'a> ::specs::System<'a> for RocketLauncherSystem {
     type SystemData = (
         ::specs::WriteStorage<'a, ::component::RocketLauncher>,
+        ::specs::ReadStorage<'a, ::component::Visibility>,
         ::specs::ReadExpect<'a, ::resource::UpdateTime>,
         ::specs::ReadExpect<'a, ::specs::LazyUpdate>,
     );
@@ -13,13 +14,15 @@ impl<'a> ::specs::System<'a> for RocketLauncherSystem {
         &mut self,
         (
             mut rocket_launchers,
+            visibilities,
             update_time,
             lazy_update,
         ): Self::SystemData,
     ) {
-        for rocket_launcher in (&mut rocket_launchers).join() {
-            rocket_launcher.timer -= update_time.0;
-            if rocket_launcher.timer <= 0.0 {
+        for (rocket_launcher, visibility) in (&mut rocket_launchers, &visibilities).join() {
+            rocket_launcher.timer = (rocket_launcher.timer - update_time.0).max(0.0);
+            // a loaded launcher waits for a player in sight
+            if rocket_launcher.timer == 0.0 && !visibility.visible.is_empty() {
                 let position = rocket_launcher.position;
                 lazy_update.exec(move |world| {
                     ::entity::create_rocket(position, world);
@@ -1,6 +1,9 @@
 use specs::Join;
-use ncollide::shape::Shape;
 
+/// Players collect targets together: targets in the shape joining all players are collected
+/// unless the shape intersects a wall
+///
+/// Captures wait for respawning players, players out of lives are left out of the shape
 pub struct TargetSystem;
 
 impl<'a> ::specs::System<'a> for TargetSystem {
@@ -9,6 +12,8 @@ impl<'a> ::specs::System<'a> for TargetSystem {
         ::specs::ReadStorage<'a, ::component::PhysicBody>,
         ::specs::ReadExpect<'a, ::resource::PhysicWorld>,
         ::specs::ReadExpect<'a, ::resource::Mode>,
+        ::specs::ReadExpect<'a, ::resource::PlayersEntities>,
+        ::specs::WriteExpect<'a, ::resource::PlayersLives>,
         ::specs::Entities<'a>,
     );
 
@@ -19,52 +24,221 @@ impl<'a> ::specs::System<'a> for TargetSystem {
             bodies,
             physic_world,
             mode,
+            players_entities,
+            mut players_lives,
             entities,
         ): Self::SystemData,
     ) {
-
         let mut wall_group = ::nphysics::object::SensorCollisionGroups::new();
         wall_group.set_membership(&[::entity::Group::Wall as usize]);
         wall_group.set_whitelist(&[::entity::Group::Wall as usize]);
-        let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_dynamic();
-        group.set_membership(&[3]);
 
         let mut target_group = ::nphysics::object::SensorCollisionGroups::new();
         target_group.set_membership(&[::entity::Group::Target as usize]);
         target_group.set_whitelist(&[::entity::Group::Target as usize]);
 
-        if mode.number_of_player() != players.join().count() {
+        let playing = (0..mode.number_of_player())
+            .filter(|&player| !players_lives.out_of_lives(player, &players_entities))
+            .count();
+        if playing != players.join().count() {
             return
         }
 
-        let (shape, position) = match mode.number_of_player() {
-            1 => {
-                let shape = ::ncollide::shape::Ball::new(::CFG.ball_radius);
-                let position = (&players, &bodies).join().next().unwrap().1.get(&physic_world).position();
-                (shape, position)
-            },
-            2 => {
-                // TODO:
-                unimplemented!();
-            },
-            3 => {
-                // TODO:
-                unimplemented!();
-            },
-            _ => unreachable!(),
+        let players_positions = (&players, &bodies, &*entities).join()
+            .map(|(_, body, entity)| (entity, body.get(&physic_world).position().translation.vector))
+            .collect::<Vec<_>>();
+        let points = players_positions.iter()
+            .map(|&(_, position)| ::na::Point3::from_coordinates(position))
+            .collect::<Vec<_>>();
+        let (shape, position) = match capture_shape(&points) {
+            Some(capture) => capture,
+            None => return,
         };
+        let aabb = shape.aabb(&position);
 
-        if physic_world.collision_world().interferences_with_aabb(&shape.aabb(position), wall_group.as_collision_groups())
-            .filter(|co| ::ncollide::query::proximity(&co.position, &*co.shape, &position, &shape, 0.0)  == ::ncollide::query::Proximity::Intersecting)
+        if physic_world.collision_world().interferences_with_aabb(&aabb, wall_group.as_collision_groups())
+            .filter(|co| ::ncollide::query::proximity(&co.position, &*co.shape, &position, &*shape, 0.0)  == ::ncollide::query::Proximity::Intersecting)
             .next()
             .is_none()
         {
-            for entity in physic_world.collision_world().interferences_with_aabb(&shape.aabb(position), target_group.as_collision_groups())
-                .filter(|co| ::ncollide::query::proximity(&co.position, &*co.shape, &position, &shape, 0.0)  == ::ncollide::query::Proximity::Intersecting)
+            let mut captured = false;
+            for entity in physic_world.collision_world().interferences_with_aabb(&aabb, target_group.as_collision_groups())
+                .filter(|co| ::ncollide::query::proximity(&co.position, &*co.shape, &position, &*shape, 0.0)  == ::ncollide::query::Proximity::Intersecting)
                 .map(|co| ::component::physic_world_object_entity(&co.data, &physic_world))
             {
                 entities.delete(entity).unwrap();
+                captured = true;
+            }
+
+            if captured {
+                players_lives.set_checkpoints(&players_positions, &players_entities);
             }
         }
     }
 }
+
+/// A ball around a single player, a segment between two players, a triangle between three and
+/// their convex hull above
+pub fn capture_shape(
+    points: &[::na::Point3<f32>],
+) -> Option<(::ncollide::shape::ShapeHandle<::na::Point3<f32>, ::na::Isometry3<f32>>, ::na::Isometry3<f32>)> {
+    let shape = match points.len() {
+        0 => return None,
+        1 => {
+            let shape = ::ncollide::shape::ShapeHandle::new(::ncollide::shape::Ball::new(::CFG.ball_radius));
+            return Some((shape, ::na::Isometry3::new(points[0].coords, ::na::zero())));
+        }
+        2 => ::ncollide::shape::ShapeHandle::new(::ncollide::shape::Segment::new(points[0], points[1])),
+        3 => ::ncollide::shape::ShapeHandle::new(::ncollide::shape::Triangle::new(points[0], points[1], points[2])),
+        _ => ::ncollide::shape::ShapeHandle::new(::ncollide::shape::ConvexHull::new(points.to_vec())),
+    };
+    Some((shape, ::na::Isometry3::identity()))
+}
+
+#[cfg(test)]
+fn capture_world(mode: ::resource::Mode) -> ::specs::World {
+    let mut world = ::specs::World::new();
+    world.register::<::component::Player>();
+    world.register::<::component::Target>();
+    world.register::<::component::PhysicBody>();
+    world.register::<::component::PhysicSensor>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(mode);
+    world.add_resource(::resource::PlayersEntities([None; 3]));
+    world.add_resource(::resource::PlayersLives::new(1));
+    world
+}
+
+#[cfg(test)]
+fn add_player(player: usize, position: ::na::Vector3<f32>, world: &mut ::specs::World) {
+    use specs::Builder;
+
+    let shape = ::ncollide::shape::Ball::new(::CFG.ball_radius);
+    let mut group = ::nphysics::object::RigidBodyCollisionGroups::new_dynamic();
+    group.set_membership(&[::entity::Group::Player as usize]);
+    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    body.set_transformation(::na::Isometry3::new(position, ::na::zero()));
+    body.set_collision_groups(group);
+    let entity = world.create_entity().with(::component::Player).build();
+    ::component::PhysicBody::add(entity, body, &mut world.write_storage(), &mut world.write_resource());
+    world.write_resource::<::resource::PlayersEntities>()[player] = Some(entity);
+}
+
+/// Which of the targets are collected
+#[cfg(test)]
+fn capture(world: &mut ::specs::World, targets: &[::na::Vector3<f32>]) -> Vec<bool> {
+    use specs::RunNow;
+
+    let alive_targets = |world: &::specs::World| {
+        (&*world.entities(), &world.read_storage::<::component::Target>())
+            .join()
+            .map(|(entity, _)| entity)
+            .collect::<Vec<_>>()
+    };
+    let mut created = vec![];
+    for &target in targets {
+        let before = alive_targets(world);
+        ::entity::create_target(target, world);
+        created.extend(alive_targets(world).into_iter().filter(|entity| !before.contains(entity)));
+    }
+
+    // add the new objects to the collision world
+    world.write_resource::<::resource::PhysicWorld>().step(0.001);
+    TargetSystem.run_now(&world.res);
+    world.maintain();
+    created.iter().map(|&target| !world.is_alive(target)).collect()
+}
+
+#[test]
+fn one_player_captures_touched_targets() {
+    let mut world = capture_world(::resource::Mode::Mode1Player);
+    add_player(0, ::na::Vector3::new(1.0, 1.0, 1.0), &mut world);
+    assert_eq!(
+        capture(&mut world, &[::na::Vector3::new(1.05, 1.0, 1.0), ::na::Vector3::new(2.0, 1.0, 1.0)]),
+        vec![true, false]
+    );
+    let checkpoint = world.read_resource::<::resource::PlayersLives>()[0].checkpoint;
+    assert_eq!(checkpoint, Some(::na::Vector3::new(1.0, 1.0, 1.0)));
+}
+
+#[test]
+fn two_players_capture_along_segment() {
+    let mut world = capture_world(::resource::Mode::Mode2Player);
+    add_player(0, ::na::Vector3::new(0.0, 0.0, 0.0), &mut world);
+    add_player(1, ::na::Vector3::new(4.0, 0.0, 0.0), &mut world);
+    assert_eq!(
+        capture(&mut world, &[::na::Vector3::new(2.0, 0.0, 0.0), ::na::Vector3::new(2.0, 1.0, 0.0)]),
+        vec![true, false]
+    );
+    {
+        // each player restarts from where it stood
+        let lives = world.read_resource::<::resource::PlayersLives>();
+        assert_eq!(lives[0].checkpoint, Some(::na::Vector3::new(0.0, 0.0, 0.0)));
+        assert_eq!(lives[1].checkpoint, Some(::na::Vector3::new(4.0, 0.0, 0.0)));
+    }
+
+    // a wall across the segment blocks the capture
+    ::entity::create_wall(::na::Vector3::new(3.0, 0.0, 0.0), ::na::Vector3::new(0.25, 0.5, 0.5), &mut world);
+    assert_eq!(capture(&mut world, &[::na::Vector3::new(1.0, 0.0, 0.0)]), vec![false]);
+}
+
+#[test]
+fn three_players_capture_inside_triangle() {
+    let mut world = capture_world(::resource::Mode::Mode3Player);
+    add_player(0, ::na::Vector3::new(0.0, 0.0, 0.0), &mut world);
+    add_player(1, ::na::Vector3::new(4.0, 0.0, 0.0), &mut world);
+    add_player(2, ::na::Vector3::new(0.0, 4.0, 0.0), &mut world);
+    assert_eq!(
+        capture(
+            &mut world,
+            &[
+                ::na::Vector3::new(1.0, 1.0, 0.0),
+                ::na::Vector3::new(3.0, 3.0, 0.0),
+                ::na::Vector3::new(1.0, 1.0, 1.0),
+            ]
+        ),
+        vec![true, false, false]
+    );
+}
+
+#[test]
+fn capture_waits_for_all_players() {
+    let mut world = capture_world(::resource::Mode::Mode3Player);
+    add_player(0, ::na::Vector3::new(0.0, 0.0, 0.0), &mut world);
+    add_player(1, ::na::Vector3::new(4.0, 0.0, 0.0), &mut world);
+    assert_eq!(capture(&mut world, &[::na::Vector3::new(2.0, 0.0, 0.0)]), vec![false]);
+}
+
+#[test]
+fn capture_without_players_out_of_lives() {
+    let mut world = capture_world(::resource::Mode::Mode3Player);
+    add_player(0, ::na::Vector3::new(0.0, 0.0, 0.0), &mut world);
+    add_player(1, ::na::Vector3::new(4.0, 0.0, 0.0), &mut world);
+    // captures wait for the third player to respawn
+    assert_eq!(capture(&mut world, &[::na::Vector3::new(2.0, 0.0, 0.0)]), vec![false]);
+
+    {
+        let mut lives = world.write_resource::<::resource::PlayersLives>();
+        lives[2].lives = 0;
+        lives[2].respawn_timer = None;
+    }
+    assert_eq!(capture(&mut world, &[::na::Vector3::new(2.0, 0.0, 0.0)]), vec![true]);
+}
+
+#[test]
+fn convex_hull_for_many_players() {
+    let points = [
+        ::na::Point3::new(0.0, 0.0, 0.0),
+        ::na::Point3::new(4.0, 0.0, 0.0),
+        ::na::Point3::new(0.0, 4.0, 0.0),
+        ::na::Point3::new(0.0, 0.0, 4.0),
+    ];
+    let (shape, position) = capture_shape(&points).unwrap();
+    let target = ::ncollide::shape::Ball::new(0.1);
+    let proximity = |at: ::na::Vector3<f32>| {
+        ::ncollide::query::proximity(&position, &*shape, &::na::Isometry3::new(at, ::na::zero()), &target, 0.0)
+    };
+    assert_eq!(proximity(::na::Vector3::new(1.0, 1.0, 1.0)), ::ncollide::query::Proximity::Intersecting);
+    assert_eq!(proximity(::na::Vector3::new(3.0, 3.0, 3.0)), ::ncollide::query::Proximity::Disjoint);
+    assert!(capture_shape(&[]).is_none());
+}
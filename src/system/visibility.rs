@@ -0,0 +1,137 @@
+use specs::Join;
+
+/// Cast rays from entities with visibility to players, only walls block them
+pub struct VisibilitySystem;
+
+impl<'a> ::specs::System<'a> for VisibilitySystem {
+    type SystemData = (
+        ::specs::ReadStorage<'a, ::component::Player>,
+        ::specs::ReadStorage<'a, ::component::PhysicBody>,
+        ::specs::ReadStorage<'a, ::component::RocketLauncher>,
+        ::specs::WriteStorage<'a, ::component::Visibility>,
+        ::specs::ReadExpect<'a, ::resource::UpdateTime>,
+        ::specs::ReadExpect<'a, ::resource::PhysicWorld>,
+        ::specs::Entities<'a>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            players,
+            bodies,
+            rocket_launchers,
+            mut visibilities,
+            update_time,
+            physic_world,
+            entities,
+        ): Self::SystemData,
+    ) {
+        let mut wall_group = ::nphysics::object::SensorCollisionGroups::new();
+        wall_group.set_membership(&[::entity::Group::Wall as usize]);
+        wall_group.set_whitelist(&[::entity::Group::Wall as usize]);
+
+        let players_position = (&players, &bodies, &*entities)
+            .join()
+            .map(|(_, body, player)| (player, body.get(&physic_world).position().translation.vector))
+            .collect::<Vec<_>>();
+
+        for (visibility, entity) in (&mut visibilities, &*entities).join() {
+            // launchers have no body
+            let position = match bodies
+                .get(entity)
+                .map(|body| body.get(&physic_world).position().translation.vector)
+                .or_else(|| rocket_launchers.get(entity).map(|l| l.position.translation.vector))
+            {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let visible = players_position
+                .iter()
+                .filter(|&&(player, _)| player != entity)
+                .filter(|&&(_, player_position)| {
+                    // the direction isn't normalized so the player is at time of impact 1
+                    let ray = ::ncollide::query::Ray::new(
+                        ::na::Point3::from_coordinates(position),
+                        player_position - position,
+                    );
+                    physic_world
+                        .collision_world()
+                        .interferences_with_ray(&ray, wall_group.as_collision_groups())
+                        .all(|(_, intersection)| intersection.toi >= 1.0)
+                })
+                .map(|&(player, _)| {
+                    let since = visibility
+                        .visible
+                        .iter()
+                        .find(|&&(visible, _)| visible == player)
+                        .map_or(0.0, |&(_, since)| since + update_time.0);
+                    (player, since)
+                })
+                .collect::<Vec<_>>();
+
+            visibility.last_seen = if !visible.is_empty() {
+                Some(0.0)
+            } else {
+                visibility.last_seen.map(|last_seen| last_seen + update_time.0)
+            };
+            visibility.visible = visible;
+        }
+    }
+}
+
+#[test]
+fn wall_blocks_sight() {
+    use specs::{Builder, RunNow};
+    use world_action::WorldAction;
+
+    let mut world = ::specs::World::new();
+    world.register::<::component::Player>();
+    world.register::<::component::PhysicBody>();
+    world.register::<::component::RocketLauncher>();
+    world.register::<::component::Visibility>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::UpdateTime(0.1));
+
+    ::entity::create_wall(::na::Vector3::new(2.0, 0.0, 0.0), ::na::Vector3::new(0.5, 0.5, 0.5), &mut world);
+
+    fn create_player(position: ::na::Vector3<f32>, world: &mut ::specs::World) -> ::specs::Entity {
+        let shape = ::ncollide::shape::Ball::new(0.1);
+        let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+        body.set_transformation(::na::Isometry3::new(position, ::na::zero()));
+        let entity = world.create_entity().with(::component::Player).build();
+        ::component::PhysicBody::add(entity, body, &mut world.write_storage(), &mut world.write_resource());
+        entity
+    }
+    let hidden = create_player(::na::Vector3::new(4.0, 0.0, 0.0), &mut world);
+    let visible = create_player(::na::Vector3::new(0.0, 4.0, 0.0), &mut world);
+
+    let launcher = world
+        .create_entity()
+        .with(::component::RocketLauncher::new(::na::Isometry3::identity()))
+        .with(::component::Visibility::default())
+        .build();
+
+    // add the wall to the collision world
+    world.write_resource::<::resource::PhysicWorld>().step(0.001);
+
+    VisibilitySystem.run_now(&world.res);
+    VisibilitySystem.run_now(&world.res);
+    {
+        let visibilities = world.read_storage::<::component::Visibility>();
+        let visibility = visibilities.get(launcher).unwrap();
+        assert!(visibility.sees(visible));
+        assert!(!visibility.sees(hidden));
+        assert_eq!(visibility.visible, vec![(visible, 0.1)]);
+        assert_eq!(visibility.last_seen, Some(0.0));
+    }
+
+    world.delete_entity(visible).unwrap();
+    world.safe_maintain();
+    VisibilitySystem.run_now(&world.res);
+    VisibilitySystem.run_now(&world.res);
+    let visibilities = world.read_storage::<::component::Visibility>();
+    let visibility = visibilities.get(launcher).unwrap();
+    assert!(visibility.visible.is_empty());
+    assert_eq!(visibility.last_seen, Some(0.2));
+}
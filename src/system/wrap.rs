@@ -0,0 +1,78 @@
+use specs::Join;
+
+/// Teleport bodies across the faces of wrapped axes, velocities are kept
+pub struct WrapSystem;
+
+impl<'a> ::specs::System<'a> for WrapSystem {
+    type SystemData = (
+        ::specs::WriteStorage<'a, ::component::PhysicBody>,
+        ::specs::ReadExpect<'a, ::resource::Wrap>,
+        ::specs::WriteExpect<'a, ::resource::PhysicWorld>,
+    );
+
+    fn run(&mut self, (mut bodies, wrap, mut physic_world): Self::SystemData) {
+        if !wrap.axes.iter().any(|&a| a) {
+            return;
+        }
+
+        for body in (&mut bodies).join() {
+            let body = body.get_mut(&mut physic_world);
+            let mut position = body.position().clone();
+            let mut changed = false;
+            for i in (0..3).filter(|&i| wrap.axes[i]) {
+                let coord = &mut position.translation.vector[i];
+                if *coord < 0.0 {
+                    *coord += wrap.size[i];
+                    changed = true;
+                } else if *coord >= wrap.size[i] {
+                    *coord -= wrap.size[i];
+                    changed = true;
+                }
+            }
+            if changed {
+                body.set_transformation(position);
+            }
+        }
+    }
+}
+
+#[test]
+fn body_crossing_seam_keeps_velocity() {
+    use specs::{Builder, RunNow};
+
+    let mut world = ::specs::World::new();
+    world.register::<::component::PhysicBody>();
+    world.add_resource(::resource::PhysicWorld::new());
+    world.add_resource(::resource::Wrap {
+        axes: [true, false, false],
+        size: ::na::Vector3::new(4.0, 4.0, 4.0),
+    });
+
+    let shape = ::ncollide::shape::Ball::new(0.1);
+    let mut body = ::nphysics::object::RigidBody::new_dynamic(shape, 1.0, 0.0, 0.0);
+    body.set_transformation(::na::Isometry3::new(::na::Vector3::new(3.95, 2.0, -0.5), ::na::zero()));
+    body.set_lin_vel(::na::Vector3::new(1.0, 0.5, 0.0));
+    let entity = world.create_entity().build();
+    ::component::PhysicBody::add(entity, body, &mut world.write_storage(), &mut world.write_resource());
+
+    let state = |world: &::specs::World| {
+        let bodies = world.read_storage::<::component::PhysicBody>();
+        let physic_world = world.read_resource::<::resource::PhysicWorld>();
+        let body = bodies.get(entity).unwrap().get(&physic_world);
+        (body.position().translation.vector, body.lin_vel())
+    };
+
+    // the body goes through the face along x, the unwrapped z axis is left alone
+    world.write_resource::<::resource::PhysicWorld>().step(0.1);
+    WrapSystem.run_now(&world.res);
+    let (position, velocity) = state(&world);
+    assert!((position - ::na::Vector3::new(0.05, 2.05, -0.5)).norm() < 1e-4);
+    assert!((velocity - ::na::Vector3::new(1.0, 0.5, 0.0)).norm() < 1e-4);
+
+    // it keeps going from the opposite face
+    world.write_resource::<::resource::PhysicWorld>().step(0.1);
+    WrapSystem.run_now(&world.res);
+    let (position, velocity) = state(&world);
+    assert!((position - ::na::Vector3::new(0.15, 2.1, -0.5)).norm() < 1e-4);
+    assert!((velocity - ::na::Vector3::new(1.0, 0.5, 0.0)).norm() < 1e-4);
+}
@@ -1,13 +1,14 @@
 use rand::distributions::{Distribution, Standard};
+use rand::Rng;
 
-pub fn generate_texture(
+pub fn generate_texture<R: Rng>(
     width: u32,
     height: u32,
     layers: u32,
     filter: ::image::FilterType,
     absissa_continuous: bool,
+    rng: &mut R,
 ) -> ::image::ImageBuffer<::image::Luma<u8>, Vec<u8>> {
-    let mut rng = ::rand::thread_rng();
     let mut deepness = layers - 1;
 
     let tmp_width = if absissa_continuous { width * 2 } else { width };
@@ -22,7 +23,7 @@ pub fn generate_texture(
 
         let data = (0..sub_image_width*sub_image_height)
             // IDEA: other distributions for example: [0..1]^2 * 255
-            .map(|_| Standard.sample(&mut rng))
+            .map(|_| Standard.sample(rng))
             .collect::<Vec<_>>();
 
         let image =
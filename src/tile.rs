@@ -1,297 +1,143 @@
-use rand::{thread_rng, Rng};
+use rand::Rng;
+use std::collections::HashMap;
 use std::collections::HashSet;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, EnumIterator)]
-pub enum TileSize {
-    T1x1,
-    T1x2,
-    T2x1,
-    T2x2,
-    T2x3,
-    T3x2,
-    T3x3,
+/// Size of a tile in subdivided cells
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct TileSize {
+    width: isize,
+    height: isize,
 }
 
-impl<'a> TileSize {
-    pub fn size(&self) -> (isize, isize) {
-        match *self {
-            TileSize::T1x1 => (1, 1),
-            TileSize::T1x2 => (1, 2),
-            TileSize::T2x1 => (2, 1),
-            TileSize::T2x2 => (2, 2),
-            TileSize::T2x3 => (2, 3),
-            TileSize::T3x2 => (3, 2),
-            TileSize::T3x3 => (3, 3),
+impl TileSize {
+    pub fn new(width: isize, height: isize) -> Self {
+        assert!(width > 0 && height > 0, "invalid tile size");
+        TileSize { width, height }
+    }
+
+    /// All sizes up to the maximum
+    pub fn all(max_size: isize) -> Vec<Self> {
+        let mut sizes = vec![];
+        for width in 1..max_size + 1 {
+            for height in 1..max_size + 1 {
+                sizes.push(TileSize::new(width, height));
+            }
         }
+        sizes
+    }
+
+    pub fn size(&self) -> (isize, isize) {
+        (self.width, self.height)
     }
 
     pub fn width(&self) -> isize {
-        self.size().0
+        self.width
     }
 
     pub fn height(&self) -> isize {
-        self.size().1
+        self.height
     }
+}
+
+/// How wall faces are covered with tiles
+#[derive(Clone, Debug)]
+pub struct Tiling {
+    /// Maximum width and height of a tile in subdivided cells
+    pub max_size: isize,
+    /// Tiles are chosen with a probability proportional to their area to this power
+    pub size_weight: f32,
+    /// Number of divisions of the side of a cell, tiles are not aligned on cells when above 1
+    pub subdivision: isize,
+}
 
-    fn from_size(width: isize, height: isize) -> Self {
-        match (width, height) {
-            (1, 1) => TileSize::T1x1,
-            (2, 1) => TileSize::T2x1,
-            (1, 2) => TileSize::T1x2,
-            (2, 2) => TileSize::T2x2,
-            (3, 2) => TileSize::T3x2,
-            (2, 3) => TileSize::T2x3,
-            (3, 3) => TileSize::T3x3,
-            _ => panic!("invalid tile size"),
+impl Tiling {
+    pub fn from_configuration() -> Self {
+        Tiling {
+            max_size: ::CFG.tile_max_size,
+            size_weight: ::CFG.tile_size_weight,
+            subdivision: ::CFG.tile_subdivision,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Tile {
     pub position: ::na::Isometry3<f32>,
     pub size: TileSize,
     pub width: f32,
     pub height: f32,
+    /// Color of the wall region
+    pub color: ::colors::GenPale,
 }
 
-/// Take a random cell in a face insert one largest tile on it. Continue to cover all faces
-pub fn build_maze(maze: &::maze::Maze<::na::U3>) -> Vec<Tile> {
+/// Take a random subdivided face and insert a random tile on it, larger tiles are more
+/// likely. Continue to cover all faces
+///
+/// Tiles take the color of their walls, the faces of a tile belong to one wall region
+pub fn build_maze<R: Rng>(
+    maze: &::maze::Maze<::na::U3>,
+    colors: &HashMap<::na::Vector3<isize>, ::colors::GenPale>,
+    tiling: &Tiling,
+    rng: &mut R,
+) -> Vec<Tile> {
+    /// Position is in subdivided cells, the coordinate along the normal is the one of the
+    /// first subdivision of the cell
     #[derive(Hash, PartialEq, Eq, Clone)]
     struct Face {
         normal: ::na::Vector3<isize>,
         position: ::na::Vector3<isize>,
     }
 
-    impl Face {
-        fn shift(&self, shift: ::na::Vector3<isize>) -> Face {
-            Face {
-                normal: self.normal,
-                position: self.position + shift,
+    /// Faces of the tile of the size covering the face at the offset
+    fn tile_faces(
+        face: &Face,
+        size: TileSize,
+        offset: (isize, isize),
+        faces: &HashSet<Face>,
+    ) -> Option<Vec<Face>> {
+        let (left, down) = left_down(face.normal);
+        let corner = face.position - left * offset.0 - down * offset.1;
+        let mut tile = vec![];
+        for i in 0..size.width() {
+            for j in 0..size.height() {
+                let face = Face {
+                    normal: face.normal,
+                    position: corner + left * i + down * j,
+                };
+                if !faces.contains(&face) {
+                    return None;
+                }
+                tile.push(face);
             }
         }
+        Some(tile)
     }
 
-    fn one_largest(face: &Face, faces: &HashSet<Face>) -> Vec<Face> {
-        let mut rng = ::rand::thread_rng();
-        let mut tiles = all_9(face, faces);
-        if !tiles.is_empty() {
-            rng.shuffle(&mut tiles);
-            return tiles.swap_remove(0);
-        }
-        let mut tiles = all_6(face, faces);
-        if !tiles.is_empty() {
-            rng.shuffle(&mut tiles);
-            return tiles.swap_remove(0);
-        }
-        let mut tiles = all_4(face, faces);
-        if !tiles.is_empty() {
-            rng.shuffle(&mut tiles);
-            return tiles.swap_remove(0);
-        }
-        let mut tiles = all_2(face, faces);
-        if !tiles.is_empty() {
-            rng.shuffle(&mut tiles);
-            return tiles.swap_remove(0);
-        }
-        let mut tiles = all_1(face, faces);
-        if !tiles.is_empty() {
-            rng.shuffle(&mut tiles);
-            return tiles.swap_remove(0);
-        }
-        unreachable!();
-    }
-
-    fn all_1(face: &Face, faces: &HashSet<Face>) -> Vec<Vec<Face>> {
-        if faces.contains(&face) {
-            vec![vec![face.clone()]]
-        } else {
-            vec![]
-        }
-    }
-
-    fn all_2(face: &Face, faces: &HashSet<Face>) -> Vec<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        vec![
-            down_1x2(face, faces),
-            down_1x2(&face.shift(down), faces),
-            left_2x1(face, faces),
-            left_2x1(&face.shift(left), faces),
-        ].iter()
-            .cloned()
-            .filter_map(|tile| tile)
-            .collect::<Vec<_>>()
-    }
-
-    fn all_4(face: &Face, faces: &HashSet<Face>) -> Vec<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        vec![
-            down_left_2x2(face, faces),
-            down_left_2x2(&face.shift(left), faces),
-            down_left_2x2(&face.shift(down), faces),
-            down_left_2x2(&face.shift(down + left), faces),
-        ].iter()
-            .cloned()
-            .filter_map(|tile| tile)
-            .collect::<Vec<_>>()
-    }
-
-    fn all_6(face: &Face, faces: &HashSet<Face>) -> Vec<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        vec![
-            down_3x2(face, faces),
-            down_3x2(&face.shift(down), faces),
-            left_2x3(face, faces),
-            left_2x3(&face.shift(left), faces),
-        ].iter()
-            .cloned()
-            .filter_map(|tile| tile)
-            .collect::<Vec<_>>()
-    }
-
-    fn all_9(face: &Face, faces: &HashSet<Face>) -> Vec<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        vec![
-            centered_3x3(face, faces),
-            centered_3x3(&face.shift(-left), faces),
-            centered_3x3(&face.shift(left), faces),
-            centered_3x3(&face.shift(-down), faces),
-            centered_3x3(&face.shift(down), faces),
-            centered_3x3(&face.shift(-left - down), faces),
-            centered_3x3(&face.shift(left - down), faces),
-            centered_3x3(&face.shift(-left + down), faces),
-            centered_3x3(&face.shift(left + down), faces),
-        ].iter()
-            .cloned()
-            .filter_map(|tile| tile)
-            .collect::<Vec<_>>()
-    }
-
-    fn down_1x2(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (_, down) = left_down(face.normal);
-        let tiles = [face.position, face.position - down]
-            .iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
-            .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
+    fn random_tile<R: Rng>(face: &Face, faces: &HashSet<Face>, tiling: &Tiling, rng: &mut R) -> Vec<Face> {
+        let mut tiles = vec![];
+        for size in TileSize::all(tiling.max_size) {
+            for x in 0..size.width() {
+                for y in 0..size.height() {
+                    if let Some(tile) = tile_faces(face, size, (x, y), faces) {
+                        tiles.push(tile);
+                    }
+                }
+            }
         }
-    }
 
-    fn left_2x1(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (left, _) = left_down(face.normal);
-        let tiles = [face.position, face.position - left]
+        let weights = tiles
             .iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
+            .map(|tile| (tile.len() as f32).powf(tiling.size_weight))
             .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
-        }
-    }
-
-    fn down_left_2x2(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        let tiles = [
-            face.position,
-            face.position - left,
-            face.position - left - down,
-            face.position - down,
-        ].iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
-            .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
-        }
-    }
-
-    fn left_2x3(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        let tiles = [
-            face.position,
-            face.position + down,
-            face.position - down,
-            face.position - left,
-            face.position - left + down,
-            face.position - left - down,
-        ].iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
-            .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
-        }
-    }
-
-    fn down_3x2(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        let tiles = [
-            face.position,
-            face.position + left,
-            face.position - left,
-            face.position + down,
-            face.position + down + left,
-            face.position + down - left,
-        ].iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
-            .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
-        }
-    }
-
-    fn centered_3x3(face: &Face, faces: &HashSet<Face>) -> Option<Vec<Face>> {
-        let (left, down) = left_down(face.normal);
-        let tiles = [
-            face.position,
-            face.position + left,
-            face.position - left,
-            face.position + down,
-            face.position - down,
-            face.position + left + down,
-            face.position - left + down,
-            face.position + left - down,
-            face.position - left - down,
-        ].iter()
-            .map(|position| Face {
-                normal: face.normal,
-                position: *position,
-            })
-            .collect::<Vec<_>>();
-
-        if tiles.iter().all(|face| faces.contains(face)) {
-            Some(tiles)
-        } else {
-            None
+        let mut choice = rng.gen::<f32>() * weights.iter().sum::<f32>();
+        for (tile, weight) in tiles.iter().zip(&weights) {
+            if choice < *weight {
+                return tile.clone();
+            }
+            choice -= weight;
         }
+        // the face itself is always a tile
+        tiles.pop().unwrap()
     }
 
     fn left_down(normal: ::na::Vector3<isize>) -> (::na::Vector3<isize>, ::na::Vector3<isize>) {
@@ -343,60 +189,70 @@ pub fn build_maze(maze: &::maze::Maze<::na::U3>) -> Vec<Tile> {
         }
     }
 
-    let mut faces = maze.walls
-        .iter()
-        .flat_map(|cell| {
-            [
-                ::na::Vector3::new(-1, 0, 0),
-                ::na::Vector3::new(1, 0, 0),
-                ::na::Vector3::new(0, -1, 0),
-                ::na::Vector3::new(0, 1, 0),
-                ::na::Vector3::new(0, 0, -1),
-                ::na::Vector3::new(0, 0, 1),
-            ].iter()
-                .filter(|normal| !maze.walls.contains(&(cell + *normal)))
-                .map(|normal| Face {
-                    normal: *normal,
-                    position: *cell,
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<HashSet<_>>();
+    let sub = tiling.subdivision;
+    let mut faces = HashSet::new();
+    for cell in &maze.walls {
+        for normal in &[
+            ::na::Vector3::new(-1, 0, 0),
+            ::na::Vector3::new(1, 0, 0),
+            ::na::Vector3::new(0, -1, 0),
+            ::na::Vector3::new(0, 1, 0),
+            ::na::Vector3::new(0, 0, -1),
+            ::na::Vector3::new(0, 0, 1),
+        ] {
+            if maze.walls.contains(&(cell + normal)) {
+                continue;
+            }
+            let (left, down) = left_down(*normal);
+            let left = left.map(|c| c.abs());
+            let down = down.map(|c| c.abs());
+            for i in 0..sub {
+                for j in 0..sub {
+                    faces.insert(Face {
+                        normal: *normal,
+                        position: cell * sub + left * i + down * j,
+                    });
+                }
+            }
+        }
+    }
 
     let mut tiles = vec![];
     let mut face_random_list = faces.iter().cloned().collect::<Vec<_>>();
-    thread_rng().shuffle(&mut face_random_list);
+    // sort first so the shuffle only depends on the rng
+    face_random_list.sort_by(|a, b| {
+        a.position
+            .iter()
+            .cmp(b.position.iter())
+            .then(a.normal.iter().cmp(b.normal.iter()))
+    });
+    rng.shuffle(&mut face_random_list);
     for face in face_random_list {
         if faces.contains(&face) {
-            let largest_tile = one_largest(&face, &faces);
-
-            let mut x_min = ::std::isize::MAX;
-            let mut x_max = ::std::isize::MIN;
-            let mut y_min = ::std::isize::MAX;
-            let mut y_max = ::std::isize::MIN;
-            let mut z_min = ::std::isize::MAX;
-            let mut z_max = ::std::isize::MIN;
+            let tile = random_tile(&face, &faces, tiling, rng);
 
-            let normal = largest_tile[0].normal;
+            let normal = tile[0].normal;
             let (left, down) = left_down(normal);
+            let cell = tile[0].position.map(|c| floor_div(c, sub));
+            let color = colors[&cell];
 
-            for face in largest_tile {
+            let mut min = ::na::Vector3::from_element(::std::isize::MAX);
+            let mut max = ::na::Vector3::from_element(::std::isize::MIN);
+            for face in tile {
                 faces.remove(&face);
-                x_min = x_min.min(face.position[0]);
-                x_max = x_max.max(face.position[0]);
-                y_min = y_min.min(face.position[1]);
-                y_max = y_max.max(face.position[1]);
-                z_min = z_min.min(face.position[2]);
-                z_max = z_max.max(face.position[2]);
+                for i in 0..3 {
+                    min[i] = min[i].min(face.position[i]);
+                    max[i] = max[i].max(face.position[i]);
+                }
             }
 
-            let size = ::na::Vector3::new(x_max - x_min + 1, y_max - y_min + 1, z_max - z_min + 1);
+            let mut size = max - min + ::na::Vector3::from_element(1);
+            // the tile lies on the face of the cell
+            let normal_axis = (0..3).find(|&i| normal[i] != 0).unwrap();
+            size[normal_axis] = sub;
 
-            let translation = ::na::Vector3::new(
-                x_min as f32 + size[0] as f32 / 2.0 + normal[0] as f32 / 2.0,
-                y_min as f32 + size[1] as f32 / 2.0 + normal[1] as f32 / 2.0,
-                z_min as f32 + size[2] as f32 / 2.0 + normal[2] as f32 / 2.0,
-            );
+            let translation = (min.map(|c| c as f32) + size.map(|c| c as f32) / 2.0) / sub as f32
+                + normal.map(|c| c as f32) / 2.0;
 
             let position = ::na::Isometry3::from_parts(
                 ::na::Translation::from_vector(translation),
@@ -408,12 +264,59 @@ pub fn build_maze(maze: &::maze::Maze<::na::U3>) -> Vec<Tile> {
 
             tiles.push(Tile {
                 position: position,
-                size: TileSize::from_size(width, height),
-                width: width as f32,
-                height: height as f32,
+                size: TileSize::new(width, height),
+                width: width as f32 / sub as f32,
+                height: height as f32 / sub as f32,
+                color,
             });
         }
     }
 
     tiles
 }
+
+fn floor_div(a: isize, b: isize) -> isize {
+    if a < 0 {
+        (a - b + 1) / b
+    } else {
+        a / b
+    }
+}
+
+#[test]
+fn tiles_cover_subdivided_faces() {
+    let mut maze = ::maze::Maze::new_kruskal(
+        ::na::Vector3::new(7, 7, 7),
+        5.0,
+        ::na::zero(),
+        &mut ::util::seeded_rng(0),
+    );
+    maze.reduce(1);
+    let colors = maze.walls.iter().map(|wall| (wall, ::colors::GenPale::Color0)).collect();
+    let walls = &maze.walls;
+    let faces = walls
+        .iter()
+        .flat_map(|cell| {
+            maze.neighbours
+                .iter()
+                .filter(move |n| !walls.contains(&(cell + *n)))
+        })
+        .count() as isize;
+
+    for &(max_size, subdivision) in &[(3, 1), (5, 2)] {
+        let tiling = Tiling {
+            max_size,
+            size_weight: 2.0,
+            subdivision,
+        };
+        let tiles = build_maze(&maze, &colors, &tiling, &mut ::util::seeded_rng(0));
+        let area = tiles
+            .iter()
+            .map(|tile| {
+                assert!(tile.size.width() <= max_size && tile.size.height() <= max_size);
+                tile.size.width() * tile.size.height()
+            })
+            .sum::<isize>();
+        assert_eq!(area, faces * subdivision * subdivision);
+    }
+}
@@ -1,25 +1,84 @@
-use rand::{thread_rng, Rng};
-use std::collections::HashSet;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 use itertools::Itertools;
-use std::f32::consts::{FRAC_PI_2, PI};
 
 pub const RADIUS: f32 = 0.05;
 
+/// Tube pieces, each made of arms going from the center of a cell toward its neighbours
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, EnumIterator)]
 pub enum Shape {
     /// Line along y axis
     Line,
     /// Angle from y axis to x axis
     Angle,
+    /// Line along y axis with a branch to x axis
+    Junction,
+    /// Lines along x and y axes
+    Cross,
+    /// Tube from y axis ending in the cell
+    EndCap,
+    /// Line along the diagonal of x and y axes
+    Diagonal,
+    /// Tube from the diagonal of x and y axes ending in the cell
+    DiagonalCap,
 }
 
 impl Shape {
-    pub fn obj(&self) -> String {
-        match *self {
-            Shape::Line => include_str!("line.obj").to_string(),
-            Shape::Angle => include_str!("angle.obj").to_string(),
+    /// Arms of the piece, as offsets to the neighbour cells they lead to
+    pub fn arms(&self) -> Vec<::na::Vector3<isize>> {
+        let arms: &[[isize; 3]] = match *self {
+            Shape::Line => &[[0, -1, 0], [0, 1, 0]],
+            Shape::Angle => &[[0, -1, 0], [1, 0, 0]],
+            Shape::Junction => &[[0, -1, 0], [0, 1, 0], [1, 0, 0]],
+            Shape::Cross => &[[0, -1, 0], [0, 1, 0], [-1, 0, 0], [1, 0, 0]],
+            Shape::EndCap => &[[0, -1, 0]],
+            Shape::Diagonal => &[[-1, -1, 0], [1, 1, 0]],
+            Shape::DiagonalCap => &[[-1, -1, 0]],
+        };
+        arms.iter().map(|a| ::na::Vector3::new(a[0], a[1], a[2])).collect()
+    }
+
+    /// Cuboid of each arm: position in the piece and half extents
+    ///
+    /// Arms overlap at the center of the piece so angles are closed
+    pub fn arm_cuboids(&self) -> Vec<(::na::Isometry3<f32>, ::na::Vector3<f32>)> {
+        self.arms()
+            .iter()
+            .map(|arm| {
+                let direction = arm.map(|c| c as f32);
+                let length = direction.norm() / 2.0;
+                let half_length = (length + RADIUS) / 2.0;
+                let rotation = ::na::UnitQuaternion::rotation_between(&-::na::Vector3::y(), &direction)
+                    .unwrap_or_else(|| {
+                        ::na::UnitQuaternion::from_axis_angle(&::na::Vector3::z_axis(), ::std::f32::consts::PI)
+                    });
+                let translation = direction.normalize() * (length - half_length);
+                (
+                    ::na::Isometry3::from_parts(::na::Translation::from_vector(translation), rotation),
+                    ::na::Vector3::new(RADIUS, half_length, RADIUS),
+                )
+            })
+            .collect()
+    }
+}
+
+/// The 24 rotations of the cube
+fn cube_rotations() -> Vec<::na::Matrix3<isize>> {
+    let mut rotations = vec![];
+    let permutations = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+    for permutation in &permutations {
+        for signs in 0..8 {
+            let mut rotation = ::na::Matrix3::zeros();
+            for (row, &column) in permutation.iter().enumerate() {
+                rotation[(row, column)] = if signs & (1 << row) == 0 { 1 } else { -1 };
+            }
+            let determinant = rotation.map(|c| c as f32).determinant();
+            if determinant > 0.0 {
+                rotations.push(rotation);
+            }
         }
     }
+    rotations
 }
 
 #[derive(Debug)]
@@ -28,16 +87,20 @@ pub struct Tube {
     pub shape: Shape,
 }
 
-pub fn generate_paths(
+pub fn generate_paths<R: Rng>(
     extra_paths: usize,
     maze: &mut ::maze::Maze<::na::U3>,
+    rng: &mut R,
 ) -> Vec<Vec<::na::Vector3<isize>>> {
-    maze.extend(2);
+    let offset = maze.extend(2);
     maze.circle();
 
     let mut wall_parts = maze.compute_zones(|maze, cell| maze.walls.contains(cell));
-    wall_parts.retain(|part| !part.iter().any(|&cell| cell == ::na::zero()));
-    thread_rng().shuffle(&mut wall_parts);
+    // the circle only exists if some axis is not wrapped
+    if offset != ::na::zero() {
+        wall_parts.retain(|part| !part.iter().any(|&cell| cell == ::na::zero()));
+    }
+    rng.shuffle(&mut wall_parts);
 
     let mut wall_parts_neighbours = wall_parts
         .iter()
@@ -45,9 +108,12 @@ pub fn generate_paths(
             let mut neighbours = walls.iter()
                 // tuple with neighbour and origin
                 .flat_map(|wall| maze.neighbours.iter().map(|n| (n+wall, wall)).collect::<Vec<_>>())
+                // paths don't go across the faces of wrapped axes
+                .filter(|&(neighbour, _)| maze.is_inside(&neighbour))
                 .collect::<HashSet<_>>();
             let mut neighbours = neighbours.drain().collect::<Vec<_>>();
-            thread_rng().shuffle(&mut neighbours);
+            neighbours.sort_by(|a, b| a.0.iter().cmp(b.0.iter()).then(a.1.iter().cmp(b.1.iter())));
+            rng.shuffle(&mut neighbours);
             neighbours
         })
         .collect::<Vec<_>>();
@@ -75,7 +141,7 @@ pub fn generate_paths(
             }
         };
 
-        for &cell in &path {
+        for cell in &path {
             maze.walls.insert(cell);
         }
         for part in &mut wall_parts_neighbours {
@@ -85,86 +151,224 @@ pub fn generate_paths(
         paths.push(path);
     }
 
-    // shift 2 because maze has been extended
     for path in &mut paths {
         for cell in path {
-            *cell -= ::na::Vector3::new(2, 2, 2);
+            *cell -= offset;
         }
     }
 
-    maze.reduce(2);
+    maze.reduce_by(offset);
 
     paths
 }
 
 // TODO: extra tubes or tubes only ??
-pub fn build_tubes(extra_tubes: usize, maze: &mut ::maze::Maze<::na::U3>) -> Vec<Tube> {
-    let paths = generate_paths(extra_tubes, maze);
-    let mut tubes = vec![];
-    for (start, tube, end) in paths.iter().flat_map(|path| path.iter().tuple_windows()) {
-        let v = end - start;
-        let translation = ::na::Translation::from_vector(::util::to_world(tube, 1.0));
+/// Build tube pieces along paths returned by `generate_paths`
+///
+/// Paths are merged into one graph so crossing and branching paths get junctions and
+/// crosses. Cells without a matching piece get one cap per arm, arms along the diagonal of
+/// the cube are not supported.
+pub fn build_tubes(paths: &[Vec<::na::Vector3<isize>>]) -> Vec<Tube> {
+    // the first and last cells of paths are in walls
+    let pieces = paths
+        .iter()
+        .filter(|path| path.len() >= 2)
+        .flat_map(|path| path[1..path.len() - 1].iter().cloned())
+        .collect::<HashSet<_>>();
 
-        if v.iter().any(|&coord| coord.abs() == 2) {
-            // Line
-            let rotation = ::na::UnitQuaternion::rotation_between(
-                &::na::Vector3::y(),
-                &::na::Vector3::new(v[0] as f32, v[1] as f32, v[2] as f32),
-            ).unwrap_or(::na::one());
+    let mut arms = HashMap::new();
+    for (a, b) in paths.iter().flat_map(|path| path.iter().tuple_windows()) {
+        if pieces.contains(a) {
+            arms.entry(*a).or_insert_with(HashSet::new).insert(b - a);
+        }
+        if pieces.contains(b) {
+            arms.entry(*b).or_insert_with(HashSet::new).insert(a - b);
+        }
+    }
 
-            tubes.push(Tube {
-                position: ::na::Isometry3::from_parts(translation, rotation),
-                shape: Shape::Line,
-            });
-        } else {
-            // Angle
-            let yv = tube - start;
-            let first_rotation = if yv[0].abs() == 1 {
-                ::na::UnitQuaternion::from_axis_angle(
-                    &::na::Vector3::z_axis(),
-                    -yv[0].signum() as f32 * FRAC_PI_2,
-                )
-            } else if yv[1] == 1 {
-                ::na::one()
-            } else if yv[1] == -1 {
-                ::na::UnitQuaternion::from_axis_angle(&::na::Vector3::z_axis(), PI)
-            } else if yv[2].abs() == 1 {
-                ::na::UnitQuaternion::from_axis_angle(
-                    &::na::Vector3::x_axis(),
-                    yv[2].signum() as f32 * FRAC_PI_2,
-                )
-            } else {
-                unreachable!();
-            };
-
-            let xv = end - tube;
-            let xv_float = ::na::Vector3::from_iterator(xv.iter().map(|&c| c as f32));
-            // TODO: if the inverse cost too much to compute we can cache it as there is only 5 rotation
-            let xv_float_trans =
-                (first_rotation.inverse() * ::na::Point::from_coordinates(xv_float)).coords;
-            let xv_trans =
-                ::na::Vector3::from_iterator(xv_float_trans.iter().map(|c| c.round() as isize));
-
-            let second_rotation = if xv_trans[0] == 1 {
-                ::na::one()
-            } else if xv_trans[0] == -1 {
-                ::na::UnitQuaternion::from_axis_angle(&::na::Vector3::y_axis(), PI)
-            } else if xv_trans[2].abs() == 1 {
-                ::na::UnitQuaternion::from_axis_angle(
-                    &::na::Vector3::y_axis(),
-                    -xv_trans[2].signum() as f32 * FRAC_PI_2,
-                )
-            } else {
-                unreachable!();
-            };
+    let mut cells = arms.keys().cloned().collect::<Vec<_>>();
+    // sorted so tubes don't depend on hash order
+    cells.sort_by(|a, b| a.iter().cmp(b.iter()));
+
+    let rotations = cube_rotations();
+    let oriented = |shape: Shape, cell_arms: &HashSet<::na::Vector3<isize>>| {
+        rotations
+            .iter()
+            .find(|rotation| {
+                let shape_arms = shape.arms();
+                shape_arms.len() == cell_arms.len()
+                    && shape_arms.iter().all(|arm| cell_arms.contains(&(*rotation * arm)))
+            })
+            .map(|rotation| {
+                let rotation = ::na::Rotation3::from_matrix_unchecked(rotation.map(|c| c as f32));
+                ::na::UnitQuaternion::from_rotation_matrix(&rotation)
+            })
+    };
 
-            let rotation = first_rotation * second_rotation;
+    let mut tubes = vec![];
+    for cell in cells {
+        let cell_arms = &arms[&cell];
+        let translation = ::na::Translation::from_vector(::util::to_world(&cell, 1.0));
+        let piece = [
+            Shape::Cross,
+            Shape::Junction,
+            Shape::Line,
+            Shape::Angle,
+            Shape::Diagonal,
+            Shape::EndCap,
+            Shape::DiagonalCap,
+        ].iter()
+            .filter_map(|&shape| oriented(shape, cell_arms).map(|rotation| (shape, rotation)))
+            .next();
 
+        if let Some((shape, rotation)) = piece {
             tubes.push(Tube {
                 position: ::na::Isometry3::from_parts(translation, rotation),
-                shape: Shape::Angle,
+                shape,
             });
+            continue;
+        }
+
+        let mut cell_arms = cell_arms.iter().cloned().collect::<Vec<_>>();
+        cell_arms.sort_by(|a, b| a.iter().cmp(b.iter()));
+        for arm in cell_arms {
+            let arm = Some(arm).into_iter().collect::<HashSet<_>>();
+            let cap = [Shape::EndCap, Shape::DiagonalCap]
+                .iter()
+                .filter_map(|&shape| oriented(shape, &arm).map(|rotation| (shape, rotation)))
+                .next();
+            if let Some((shape, rotation)) = cap {
+                tubes.push(Tube {
+                    position: ::na::Isometry3::from_parts(translation, rotation),
+                    shape,
+                });
+            }
         }
     }
     tubes
 }
+
+/// Polyline a rider follows through a tube
+///
+/// It goes from the wall face at the start of the path to the wall face at its end
+#[derive(Clone, Debug)]
+pub struct Rail {
+    points: Vec<::na::Vector3<f32>>,
+    /// Distance along the rail of each point
+    distances: Vec<f32>,
+}
+
+impl Rail {
+    /// Rail along a path returned by `generate_paths`
+    pub fn new(path: &[::na::Vector3<isize>], unit: f32) -> Self {
+        assert!(path.len() >= 3, "path has no tube cell");
+        let centers = path.iter()
+            .map(|cell| ::util::to_world(cell, unit))
+            .collect::<Vec<_>>();
+        let last = centers.len() - 1;
+
+        let mut points = vec![(centers[0] + centers[1]) / 2.0];
+        points.extend_from_slice(&centers[1..last]);
+        points.push((centers[last - 1] + centers[last]) / 2.0);
+
+        let mut distances = vec![0.0];
+        for (a, b) in points.iter().tuple_windows() {
+            let distance = distances.last().unwrap() + (b - a).norm();
+            distances.push(distance);
+        }
+        Rail { points, distances }
+    }
+
+    pub fn length(&self) -> f32 {
+        *self.distances.last().unwrap()
+    }
+
+    pub fn start(&self) -> ::na::Vector3<f32> {
+        self.points[0]
+    }
+
+    pub fn end(&self) -> ::na::Vector3<f32> {
+        *self.points.last().unwrap()
+    }
+
+    /// Unit vector leaving the wall at the start
+    pub fn start_normal(&self) -> ::na::Vector3<f32> {
+        (self.points[1] - self.points[0]).normalize()
+    }
+
+    /// Unit vector leaving the wall at the end
+    pub fn end_normal(&self) -> ::na::Vector3<f32> {
+        let last = self.points.len() - 1;
+        (self.points[last - 1] - self.points[last]).normalize()
+    }
+
+    /// Position and unit direction at the distance from the start, clamped to the rail
+    pub fn point_at(&self, distance: f32) -> (::na::Vector3<f32>, ::na::Vector3<f32>) {
+        let distance = distance.max(0.0).min(self.length());
+        let segment = self.distances[1..]
+            .iter()
+            .position(|&d| d >= distance)
+            .unwrap_or(self.distances.len() - 2);
+        let (a, b) = (self.points[segment], self.points[segment + 1]);
+        let segment_length = self.distances[segment + 1] - self.distances[segment];
+        let t = (distance - self.distances[segment]) / segment_length;
+        (a + (b - a) * t, (b - a) / segment_length)
+    }
+}
+
+#[test]
+fn crossing_and_branching_paths() {
+    let cell = |x, y| ::na::Vector3::new(x, y, 2);
+    let paths = vec![
+        (0..5).map(|x| cell(x, 2)).collect::<Vec<_>>(),
+        (0..5).map(|y| cell(2, y)).collect(),
+        // ends on the first path
+        vec![cell(3, 0), cell(3, 1), cell(3, 2)],
+        (6..9).map(|i| cell(i, i)).collect(),
+    ];
+    let tubes = build_tubes(&paths);
+    let tube = |x, y| {
+        let position = ::util::to_world(&cell(x, y), 1.0);
+        let tubes = tubes
+            .iter()
+            .filter(|tube| tube.position.translation.vector == position)
+            .collect::<Vec<_>>();
+        assert_eq!(tubes.len(), 1);
+        tubes[0]
+    };
+
+    assert_eq!(tubes.len(), 7);
+    assert_eq!(tube(2, 2).shape, Shape::Cross);
+    assert_eq!(tube(1, 2).shape, Shape::Line);
+    assert_eq!(tube(3, 1).shape, Shape::Line);
+    assert_eq!(tube(7, 7).shape, Shape::Diagonal);
+
+    // the branch of the junction goes toward the third path
+    let junction = tube(3, 2);
+    assert_eq!(junction.shape, Shape::Junction);
+    let branch = junction.position.rotation * ::na::Vector3::x();
+    assert!((branch + ::na::Vector3::y()).norm() < 1e-5);
+}
+
+#[test]
+fn paths_do_not_cross_wrapped_faces() {
+    // two walls across a maze wrapped on every axis, the shortest way between them is
+    // often across the faces
+    let mut maze = ::maze::walled(::na::Vector3::new(8, 3, 3), |c| c[0] != 1 && c[0] != 5);
+    for axis in 0..3 {
+        maze.set_wrap(axis, true);
+    }
+    let mut rng = ::util::seeded_rng(0);
+    let mut found = 0;
+    for _ in 0..10 {
+        let paths = generate_paths(4, &mut maze.clone(), &mut rng);
+        for path in &paths {
+            assert!(path.iter().all(|cell| maze.is_inside(cell)));
+            for (a, b) in path.iter().tuple_windows() {
+                assert!((b - a).iter().all(|c| c.abs() <= 1), "{:?} to {:?}", a, b);
+            }
+        }
+        found += paths.len();
+    }
+    assert!(found > 0);
+}
@@ -1,3 +1,4 @@
+use rand::SeedableRng;
 use rusttype::IntoGlyphId;
 
 macro_rules! try_multiple_time {
@@ -19,9 +20,8 @@ macro_rules! try_multiple_time {
 }
 
 #[inline]
-#[allow(unused)]
 pub fn to_grid(coords: &::na::Vector3<f32>, scale: f32) -> ::na::Vector3<isize> {
-    ::na::Vector3::<isize>::from_iterator(coords.iter().map(|&c| (c / scale) as isize))
+    ::na::Vector3::<isize>::from_iterator(coords.iter().map(|&c| (c / scale).floor() as isize))
 }
 
 #[inline]
@@ -33,6 +33,15 @@ pub fn to_world(coords: &::na::Vector3<isize>, scale: f32) -> ::na::Vector3<f32>
     res
 }
 
+/// Random generator whose sequence only depends on the seed
+pub fn seeded_rng(seed: u64) -> ::rand::prng::ChaChaRng {
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().take(8).enumerate() {
+        *byte = (seed >> (i * 8)) as u8;
+    }
+    ::rand::prng::ChaChaRng::from_seed(bytes)
+}
+
 pub fn menu_layout(texts: Vec<String>, cursor: Option<usize>, font: &::rusttype::Font<'static>) -> Vec<::rusttype::PositionedGlyph<'static>> {
     let v_metrics = font.v_metrics(::rusttype::Scale::uniform(::CFG.text_scale));
     let y_delta = v_metrics.ascent - v_metrics.descent + v_metrics.line_gap;
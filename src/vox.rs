@@ -0,0 +1,181 @@
+//! MagicaVoxel `.vox` import, only the first model of a file is read
+
+use maze::{Marker, Maze};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Voxels of a model and their palette indices, from 1 to 255
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoxModel {
+    pub size: ::na::Vector3<isize>,
+    pub voxels: Vec<(::na::Vector3<isize>, u8)>,
+}
+
+/// Palette indices of the voxels holding an entity, other voxels are walls
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VoxMarkers {
+    pub spawn: Option<u8>,
+    pub target: Option<u8>,
+    pub mine: Option<u8>,
+    pub rocket_launcher: Option<u8>,
+}
+
+impl VoxMarkers {
+    pub fn marker(&self, index: u8) -> Option<Marker> {
+        let some = Some(index);
+        if self.spawn == some {
+            Some(Marker::Spawn)
+        } else if self.target == some {
+            Some(Marker::Target)
+        } else if self.mine == some {
+            Some(Marker::Mine)
+        } else if self.rocket_launcher == some {
+            Some(Marker::RocketLauncher)
+        } else {
+            None
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ::failure::Error> {
+        if self.bytes.len() < len {
+            return Err(::failure::err_msg("vox file is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, ::failure::Error> {
+        let bytes = self.take(4)?;
+        Ok((0..4).map(|i| (bytes[i] as u32) << (8 * i)).sum())
+    }
+
+    /// Chunk id, content and children
+    fn chunk(&mut self) -> Result<(&'a [u8], &'a [u8], &'a [u8]), ::failure::Error> {
+        let id = self.take(4)?;
+        let content_len = self.u32()? as usize;
+        let children_len = self.u32()? as usize;
+        Ok((id, self.take(content_len)?, self.take(children_len)?))
+    }
+}
+
+impl VoxModel {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ::failure::Error> {
+        let mut bytes = vec![];
+        File::open(path)?.read_to_end(&mut bytes)?;
+        VoxModel::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, ::failure::Error> {
+        let mut reader = Reader { bytes };
+        if reader.take(4)? != b"VOX " {
+            return Err(::failure::err_msg("not a vox file"));
+        }
+        let _version = reader.u32()?;
+
+        let (id, _, children) = reader.chunk()?;
+        if id != b"MAIN" {
+            return Err(::failure::err_msg("vox file has no MAIN chunk"));
+        }
+
+        let mut children = Reader { bytes: children };
+        let mut size = None;
+        while !children.bytes.is_empty() {
+            let (id, content, _) = children.chunk()?;
+            let mut content = Reader { bytes: content };
+            if id == b"SIZE" {
+                size = Some(::na::Vector3::new(
+                    content.u32()? as isize,
+                    content.u32()? as isize,
+                    content.u32()? as isize,
+                ));
+            } else if id == b"XYZI" {
+                let size = size.ok_or_else(|| ::failure::err_msg("vox XYZI chunk before SIZE"))?;
+                let count = content.u32()? as usize;
+                let mut voxels = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let voxel = content.take(4)?;
+                    let cell = ::na::Vector3::new(voxel[0] as isize, voxel[1] as isize, voxel[2] as isize);
+                    voxels.push((cell, voxel[3]));
+                }
+                return Ok(VoxModel { size, voxels });
+            }
+            // other chunks are palette, materials and scene graph
+        }
+        Err(::failure::err_msg("vox file has no model"))
+    }
+
+    /// Maze of the size of the model with walls on its voxels, except the marked ones
+    pub fn to_maze(&self, markers: &VoxMarkers) -> (Maze<::na::U3>, Vec<(Marker, ::na::Vector3<isize>)>) {
+        let mut maze = Maze::new_rectangle(self.size);
+        let mut marked = vec![];
+        for &(cell, index) in &self.voxels {
+            match markers.marker(index) {
+                Some(marker) => marked.push((marker, cell)),
+                None => {
+                    maze.walls.insert(&cell);
+                }
+            }
+        }
+        (maze, marked)
+    }
+}
+
+#[test]
+fn parse_vox_model() {
+    fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+        for i in 0..4 {
+            bytes.push((value >> (8 * i)) as u8);
+        }
+    }
+
+    let voxels: &[[u8; 4]] = &[[0, 0, 0, 1], [1, 0, 0, 1], [2, 1, 0, 5], [0, 1, 0, 6]];
+    let mut children = vec![];
+    children.extend_from_slice(b"SIZE");
+    push_u32(&mut children, 12);
+    push_u32(&mut children, 0);
+    for &len in &[3, 2, 1] {
+        push_u32(&mut children, len);
+    }
+    children.extend_from_slice(b"XYZI");
+    push_u32(&mut children, 4 + 4 * voxels.len() as u32);
+    push_u32(&mut children, 0);
+    push_u32(&mut children, voxels.len() as u32);
+    for voxel in voxels {
+        children.extend_from_slice(voxel);
+    }
+
+    let mut bytes = b"VOX ".to_vec();
+    push_u32(&mut bytes, 150);
+    bytes.extend_from_slice(b"MAIN");
+    push_u32(&mut bytes, 0);
+    push_u32(&mut bytes, children.len() as u32);
+    bytes.extend_from_slice(&children);
+
+    let model = VoxModel::parse(&bytes).unwrap();
+    assert_eq!(model.size, ::na::Vector3::new(3, 2, 1));
+    let markers = VoxMarkers {
+        spawn: Some(5),
+        target: Some(6),
+        ..Default::default()
+    };
+    let (maze, marked) = model.to_maze(&markers);
+    assert_eq!(maze.size(), model.size);
+    assert_eq!(maze.walls.len(), 2);
+    assert_eq!(
+        marked,
+        vec![
+            (Marker::Spawn, ::na::Vector3::new(2, 1, 0)),
+            (Marker::Target, ::na::Vector3::new(0, 1, 0)),
+        ]
+    );
+
+    assert!(VoxModel::parse(&bytes[..bytes.len() - 1]).is_err());
+}